@@ -0,0 +1,161 @@
+use std::fmt;
+
+use bech32::{primitives::decode::CheckedHrpstring, Bech32m, Hrp};
+use chia::protocol::Bytes32;
+
+use crate::{Condition, Error, FlatItem};
+
+/// Why an address could not be encoded, decoded or matched with a puzzle hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    Invalid(String),
+    InvalidLength(usize),
+    WrongPrefix { expected: String, actual: String },
+    WrongPuzzleHash(Bytes32),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(message) => write!(f, "invalid address: {message}"),
+            Self::InvalidLength(length) => {
+                write!(f, "address encodes {length} bytes, expected 32")
+            }
+            Self::WrongPrefix { expected, actual } => {
+                write!(f, "address has prefix {actual}, expected {expected}")
+            }
+            Self::WrongPuzzleHash(puzzle_hash) => {
+                write!(f, "address encodes puzzle hash {puzzle_hash}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Encodes a puzzle hash as a bech32m address, such as `xch1...` or `txch1...`.
+pub fn encode_address(puzzle_hash: Bytes32, prefix: &str) -> Result<String, AddressError> {
+    let hrp = Hrp::parse(prefix).map_err(|error| AddressError::Invalid(error.to_string()))?;
+    bech32::encode::<Bech32m>(hrp, &puzzle_hash)
+        .map_err(|error| AddressError::Invalid(error.to_string()))
+}
+
+/// Decodes a bech32m address into its prefix and puzzle hash.
+pub fn decode_address(address: &str) -> Result<(String, Bytes32), AddressError> {
+    let checked = CheckedHrpstring::new::<Bech32m>(address)
+        .map_err(|error| AddressError::Invalid(error.to_string()))?;
+    let bytes: Vec<u8> = checked.byte_iter().collect();
+    let puzzle_hash = Bytes32::try_from(bytes.as_slice())
+        .map_err(|_| AddressError::InvalidLength(bytes.len()))?;
+    Ok((checked.hrp().to_lowercase(), puzzle_hash))
+}
+
+/// Checks that an address has the given prefix and encodes exactly the puzzle hash.
+pub fn verify_address(
+    address: &str,
+    puzzle_hash: Bytes32,
+    prefix: &str,
+) -> Result<(), AddressError> {
+    let (actual_prefix, decoded) = decode_address(address)?;
+    if actual_prefix != prefix {
+        return Err(AddressError::WrongPrefix {
+            expected: prefix.to_string(),
+            actual: actual_prefix,
+        });
+    }
+    if decoded != puzzle_hash {
+        return Err(AddressError::WrongPuzzleHash(decoded));
+    }
+    Ok(())
+}
+
+/// Checks the `send_address` of every `CREATE_COIN` against its puzzle hash.
+pub fn verify_addresses(items: &[FlatItem], prefix: &str) -> Vec<Error> {
+    let mut errors = Vec::new();
+
+    for FlatItem { item, path, .. } in items {
+        for (index, condition) in item.indexed_conditions() {
+            let Condition::CreateCoin {
+                puzzle_hash,
+                address,
+                ..
+            } = condition
+            else {
+                continue;
+            };
+
+            if let Err(source) = verify_address(address, *puzzle_hash, prefix) {
+                errors.push(Error::InvalidAddress {
+                    path: format!("{path}.Conditions[{index}]"),
+                    coin_id: item.coin_id,
+                    index,
+                    address: address.clone(),
+                    source,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "xch1tl7kzjr42ndy4nde5vhxvdz0tr44pax8s09z47wduplm02t7gneslty5kq";
+
+    fn puzzle_hash() -> Bytes32 {
+        let bytes = hex::decode("5ffd61487554da4acdb9a32e66344f58eb50f4c783ca2af9cde07fb7a97e44f3");
+        Bytes32::try_from(bytes.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_address() {
+        assert_eq!(encode_address(puzzle_hash(), "xch").unwrap(), ADDRESS);
+        assert_eq!(
+            decode_address(ADDRESS),
+            Ok(("xch".to_string(), puzzle_hash()))
+        );
+        assert_eq!(verify_address(ADDRESS, puzzle_hash(), "xch"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_wrong_prefix() {
+        let address = encode_address(puzzle_hash(), "txch").unwrap();
+        assert_eq!(
+            verify_address(&address, puzzle_hash(), "xch"),
+            Err(AddressError::WrongPrefix {
+                expected: "xch".to_string(),
+                actual: "txch".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_puzzle_hash() {
+        let other = Bytes32::new([0x22; 32]);
+        assert_eq!(
+            verify_address(ADDRESS, other, "xch"),
+            Err(AddressError::WrongPuzzleHash(puzzle_hash()))
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        let address = bech32::encode::<Bech32m>(Hrp::parse("xch").unwrap(), &[0x11; 20]).unwrap();
+        assert_eq!(
+            decode_address(&address),
+            Err(AddressError::InvalidLength(20))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let address = ADDRESS.replace("ty5kq", "ty5kp");
+        assert!(matches!(
+            decode_address(&address),
+            Err(AddressError::Invalid(_))
+        ));
+    }
+}
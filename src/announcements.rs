@@ -0,0 +1,330 @@
+use std::{collections::HashMap, fmt};
+
+use chia::protocol::{Bytes, Bytes32};
+use sha2::{digest::FixedOutput, Digest, Sha256};
+
+use crate::{Condition, Error, FlatItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AnnouncementKind {
+    Coin,
+    Puzzle,
+}
+
+impl fmt::Display for AnnouncementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Coin => write!(f, "coin"),
+            Self::Puzzle => write!(f, "puzzle"),
+        }
+    }
+}
+
+/// Identifies an announcement. Coin and puzzle announcement ids are hashed
+/// from different preimages, so the kind is part of the identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnnouncementKey {
+    pub kind: AnnouncementKind,
+    pub announcement_id: Bytes32,
+}
+
+impl AnnouncementKey {
+    pub fn coin(announcement_id: Bytes32) -> Self {
+        Self {
+            kind: AnnouncementKind::Coin,
+            announcement_id,
+        }
+    }
+
+    pub fn puzzle(announcement_id: Bytes32) -> Self {
+        Self {
+            kind: AnnouncementKind::Puzzle,
+            announcement_id,
+        }
+    }
+}
+
+/// A coin and the position of one of its conditions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConditionRef {
+    pub coin_id: Bytes32,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateCoinAnnouncement {
+    pub coin_id: Bytes32,
+    pub index: usize,
+    pub message: Bytes,
+    pub announcement_id: Bytes32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatePuzzleAnnouncement {
+    pub coin_id: Bytes32,
+    pub index: usize,
+    pub puzzle_hash: Bytes32,
+    pub message: Bytes,
+    pub announcement_id: Bytes32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertPuzzleAnnouncement {
+    pub coin_id: Bytes32,
+    pub index: usize,
+    pub announcement_id: Bytes32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertCoinAnnouncement {
+    pub coin_id: Bytes32,
+    pub index: usize,
+    pub announcement_id: Bytes32,
+}
+
+/// An announcement that is created, but that nothing in the block asserts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanAnnouncement {
+    pub coin_id: Bytes32,
+    pub key: AnnouncementKey,
+    pub message: Bytes,
+}
+
+/// An announcement created by more than one condition in the block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateAnnouncement {
+    pub key: AnnouncementKey,
+    pub creators: Vec<Bytes32>,
+
+    /// Whether anything asserts the announcement, making the ambiguity matter.
+    pub asserted: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Announcements {
+    /// Announcement id to every condition creating it. Usually there is only one.
+    pub create_coin: HashMap<Bytes32, Vec<CreateCoinAnnouncement>>,
+
+    /// Announcement id to every condition creating it. Coins sharing a puzzle
+    /// hash can legitimately create the same puzzle announcement.
+    pub create_puzzle: HashMap<Bytes32, Vec<CreatePuzzleAnnouncement>>,
+
+    pub assert_puzzle: Vec<AssertPuzzleAnnouncement>,
+    pub assert_coin: Vec<AssertCoinAnnouncement>,
+
+    /// Coin id to the announcements it creates.
+    pub created: HashMap<Bytes32, Vec<AnnouncementKey>>,
+
+    /// Coin id to the announcements it asserts.
+    pub asserted: HashMap<Bytes32, Vec<AnnouncementKey>>,
+
+    /// Announcement to the conditions that assert it.
+    pub asserters: HashMap<AnnouncementKey, Vec<ConditionRef>>,
+}
+
+impl Announcements {
+    /// Collects the announcements made and asserted by the items. Puzzle
+    /// announcements from coins without a puzzle hash are skipped and reported.
+    pub fn from_items(items: &[FlatItem]) -> (Self, Vec<Error>) {
+        let mut announcements = Self::default();
+        let mut errors = Vec::new();
+
+        for FlatItem { item, path, .. } in items {
+            for (index, condition) in item.indexed_conditions() {
+                match condition {
+                    Condition::CreateCoinAnnouncement { message } => {
+                        let message = message.clone();
+
+                        let mut hasher = Sha256::new();
+                        hasher.update(item.coin_id);
+                        hasher.update(&message);
+
+                        let announcement_id = Bytes32::new(hasher.finalize_fixed().into());
+
+                        announcements
+                            .add_created(item.coin_id, AnnouncementKey::coin(announcement_id));
+                        announcements
+                            .create_coin
+                            .entry(announcement_id)
+                            .or_default()
+                            .push(CreateCoinAnnouncement {
+                                coin_id: item.coin_id,
+                                index,
+                                message,
+                                announcement_id,
+                            });
+                    }
+                    Condition::CreatePuzzleAnnouncement { message } => {
+                        let Some(puzzle_hash) = item.puzzle_hash else {
+                            errors.push(Error::MissingPuzzleHash {
+                                path: format!("{path}.Conditions[{index}]"),
+                                coin_id: item.coin_id,
+                                index,
+                            });
+                            continue;
+                        };
+
+                        let message = message.clone();
+
+                        let mut hasher = Sha256::new();
+                        hasher.update(puzzle_hash);
+                        hasher.update(&message);
+
+                        let announcement_id = Bytes32::new(hasher.finalize_fixed().into());
+
+                        announcements
+                            .add_created(item.coin_id, AnnouncementKey::puzzle(announcement_id));
+                        announcements
+                            .create_puzzle
+                            .entry(announcement_id)
+                            .or_default()
+                            .push(CreatePuzzleAnnouncement {
+                                coin_id: item.coin_id,
+                                index,
+                                puzzle_hash,
+                                message,
+                                announcement_id,
+                            });
+                    }
+                    Condition::AssertCoinAnnouncement { announcement_id } => {
+                        announcements.add_asserted(
+                            item.coin_id,
+                            index,
+                            AnnouncementKey::coin(*announcement_id),
+                        );
+                        announcements.assert_coin.push(AssertCoinAnnouncement {
+                            coin_id: item.coin_id,
+                            index,
+                            announcement_id: *announcement_id,
+                        });
+                    }
+                    Condition::AssertPuzzleAnnouncement { announcement_id } => {
+                        announcements.add_asserted(
+                            item.coin_id,
+                            index,
+                            AnnouncementKey::puzzle(*announcement_id),
+                        );
+                        announcements.assert_puzzle.push(AssertPuzzleAnnouncement {
+                            coin_id: item.coin_id,
+                            index,
+                            announcement_id: *announcement_id,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        (announcements, errors)
+    }
+
+    /// Returns every coin that created the given announcement in the block.
+    pub fn creators(&self, key: AnnouncementKey) -> Vec<Bytes32> {
+        self.creations(key)
+            .into_iter()
+            .map(|(creator, _)| creator.coin_id)
+            .collect()
+    }
+
+    /// Returns every condition creating the given announcement, with its message.
+    pub fn creations(&self, key: AnnouncementKey) -> Vec<(ConditionRef, Bytes)> {
+        match key.kind {
+            AnnouncementKind::Coin => self
+                .create_coin
+                .get(&key.announcement_id)
+                .into_iter()
+                .flatten()
+                .map(|created| {
+                    let creator = ConditionRef {
+                        coin_id: created.coin_id,
+                        index: created.index,
+                    };
+                    (creator, created.message.clone())
+                })
+                .collect(),
+            AnnouncementKind::Puzzle => self
+                .create_puzzle
+                .get(&key.announcement_id)
+                .into_iter()
+                .flatten()
+                .map(|created| {
+                    let creator = ConditionRef {
+                        coin_id: created.coin_id,
+                        index: created.index,
+                    };
+                    (creator, created.message.clone())
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the announcements created more than once, along with all of
+    /// their creators. An assertion of one of these can't be attributed to a
+    /// single coin, so it is linked to every creator.
+    pub fn duplicates(&self) -> Vec<DuplicateAnnouncement> {
+        let coin = self
+            .create_coin
+            .keys()
+            .map(|&announcement_id| AnnouncementKey::coin(announcement_id));
+        let puzzle = self
+            .create_puzzle
+            .keys()
+            .map(|&announcement_id| AnnouncementKey::puzzle(announcement_id));
+
+        let mut duplicates = Vec::new();
+        for key in coin.chain(puzzle) {
+            let creators = self.creators(key);
+            if creators.len() > 1 {
+                duplicates.push(DuplicateAnnouncement {
+                    key,
+                    creators,
+                    asserted: self.asserters.contains_key(&key),
+                });
+            }
+        }
+        duplicates.sort_by_key(|duplicate| duplicate.key);
+        duplicates
+    }
+
+    /// Returns every announcement that is created but never asserted, ordered
+    /// by creator coin and announcement.
+    pub fn orphans(&self) -> Vec<OrphanAnnouncement> {
+        let coin = self
+            .create_coin
+            .values()
+            .flatten()
+            .map(|created| OrphanAnnouncement {
+                coin_id: created.coin_id,
+                key: AnnouncementKey::coin(created.announcement_id),
+                message: created.message.clone(),
+            });
+        let puzzle = self
+            .create_puzzle
+            .values()
+            .flatten()
+            .map(|created| OrphanAnnouncement {
+                coin_id: created.coin_id,
+                key: AnnouncementKey::puzzle(created.announcement_id),
+                message: created.message.clone(),
+            });
+
+        let mut orphans: Vec<OrphanAnnouncement> = coin
+            .chain(puzzle)
+            .filter(|orphan| !self.asserters.contains_key(&orphan.key))
+            .collect();
+        orphans.sort_by_key(|orphan| (orphan.coin_id, orphan.key));
+        orphans
+    }
+
+    fn add_created(&mut self, coin_id: Bytes32, key: AnnouncementKey) {
+        self.created.entry(coin_id).or_default().push(key);
+    }
+
+    fn add_asserted(&mut self, coin_id: Bytes32, index: usize, key: AnnouncementKey) {
+        self.asserted.entry(coin_id).or_default().push(key);
+        self.asserters
+            .entry(key)
+            .or_default()
+            .push(ConditionRef { coin_id, index });
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{AnnouncementGraph, Condition, FlatItem, SpendBundle};
+
+/// The XCH flowing through a set of spends, in mojos.
+///
+/// Only spent items count: their own amounts are the inputs, and the
+/// `CREATE_COIN` conditions they emit are the outputs. CAT spends are left out,
+/// since their amounts are in units of the CAT and the block doesn't say which
+/// CAT each one is, but their `RESERVE_FEE` conditions still count.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Balance {
+    /// The total amount of the coins spent.
+    pub inputs: u128,
+
+    /// The total amount of the coins created.
+    pub outputs: u128,
+
+    /// The total of every `RESERVE_FEE` condition.
+    pub reserved_fee: u128,
+
+    /// How many spent coins have no known amount, and are left out of `inputs`.
+    pub unknown_amounts: usize,
+
+    /// How many spent CAT coins are left out of `inputs` and `outputs`.
+    pub cat_spends: usize,
+}
+
+impl Balance {
+    pub fn of<'a>(items: impl IntoIterator<Item = &'a FlatItem>) -> Self {
+        let mut balance = Self::default();
+
+        for FlatItem { item, .. } in items {
+            if !item.spend {
+                continue;
+            }
+
+            let is_cat = item.is_cat();
+            match item.amount {
+                _ if is_cat => balance.cat_spends += 1,
+                Some(amount) => balance.inputs += amount as u128,
+                None => balance.unknown_amounts += 1,
+            }
+
+            for condition in item.conditions.iter() {
+                match condition {
+                    Condition::CreateCoin { amount, .. } if !is_cat => {
+                        balance.outputs += *amount as u128
+                    }
+                    Condition::ReserveFee { amount } => balance.reserved_fee += *amount as u128,
+                    _ => {}
+                }
+            }
+        }
+
+        balance
+    }
+
+    /// The inputs left over after the outputs, which go to the farmer. This is
+    /// negative if more value is created than spent, and `None` if the amount
+    /// of a spent coin is unknown.
+    pub fn implied_fee(&self) -> Option<i128> {
+        self.is_complete()
+            .then(|| self.inputs as i128 - self.outputs as i128)
+    }
+
+    /// How far the reserved fee exceeds the implied fee, if it does. Consensus
+    /// rejects a spend bundle whose `RESERVE_FEE` conditions ask for more than
+    /// it leaves over.
+    pub fn fee_shortfall(&self) -> Option<u128> {
+        let fee = self.implied_fee()?;
+        let shortfall = self.reserved_fee as i128 - fee.max(0);
+        (shortfall > 0).then_some(shortfall as u128)
+    }
+
+    /// Whether every spent coin's amount is known, so the fee is exact.
+    pub fn is_complete(&self) -> bool {
+        self.unknown_amounts == 0
+    }
+}
+
+impl SpendBundle<'_> {
+    pub fn balance(&self) -> Balance {
+        Balance::of(self.items.iter().copied())
+    }
+}
+
+impl AnnouncementGraph {
+    /// The balance of the whole block.
+    pub fn balance(&self) -> Balance {
+        Balance::of(self.items())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flatten_items, parse_items};
+
+    fn spend(coin_id: u8, ty: &str, amount: u64, conditions: &str) -> String {
+        format!(
+            r#"{{"Coin": "{}", "Coin_puzzle_hash": null, "Coin_amount": {amount},
+                 "Type": "{ty}", "Tags": null, "Spend": true, "Children": [],
+                 "Conditions": [{conditions}]}}"#,
+            hex::encode([coin_id; 32])
+        )
+    }
+
+    fn create_coin(amount: u64) -> String {
+        format!(
+            r#"{{"opcode": "CREATE_COIN", "send_puzzle": "{}", "amt": {amount},
+                 "child_coin_name": "{}", "send_address": ""}}"#,
+            hex::encode([0xaa; 32]),
+            hex::encode([0xbb; 32])
+        )
+    }
+
+    #[test]
+    fn leaves_out_cat_amounts() {
+        let reserve_fee = r#"{"opcode": "RESERVE_FEE", "vars": ["0a"]}"#;
+        let json = format!(
+            "[{}, {}]",
+            spend(1, "standard", 100, &create_coin(80)),
+            spend(
+                2,
+                "token",
+                3000,
+                &format!("{}, {reserve_fee}", create_coin(3000))
+            )
+        );
+        let items = flatten_items(parse_items(&json).unwrap());
+
+        let balance = Balance::of(&items);
+        assert_eq!(
+            balance,
+            Balance {
+                inputs: 100,
+                outputs: 80,
+                reserved_fee: 10,
+                unknown_amounts: 0,
+                cat_spends: 1,
+            }
+        );
+        assert_eq!(balance.implied_fee(), Some(20));
+        assert_eq!(balance.fee_shortfall(), None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use chia::protocol::Bytes32;
+
+use crate::{AnnouncementGraph, FlatItem};
+
+/// A set of items that only interact with each other, such as a single
+/// transaction or offer within a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendBundle<'a> {
+    pub items: Vec<&'a FlatItem>,
+}
+
+impl SpendBundle<'_> {
+    pub fn coin_ids(&self) -> Vec<Bytes32> {
+        self.items.iter().map(|flat| flat.item.coin_id).collect()
+    }
+
+    /// The items that are actually spent, as opposed to coins only created.
+    pub fn spent(&self) -> impl Iterator<Item = &FlatItem> {
+        self.items.iter().copied().filter(|flat| flat.item.spend)
+    }
+}
+
+impl AnnouncementGraph {
+    /// Groups the coins into sets connected by announcements in either
+    /// direction, or by a `CREATE_COIN` from parent to child. Coins without
+    /// any such edges form their own component.
+    pub fn components(&self) -> Vec<Vec<Bytes32>> {
+        self.connected_items()
+            .into_iter()
+            .map(|bundle| bundle.coin_ids())
+            .collect()
+    }
+
+    /// Splits the block into independent spend bundles, one per transaction,
+    /// using the same connectivity as [`AnnouncementGraph::components`].
+    /// Components without a spent coin aren't transactions and are left out.
+    /// Items keep the order they have in [`AnnouncementGraph::items`].
+    pub fn spend_bundles(&self) -> Vec<SpendBundle<'_>> {
+        self.connected_items()
+            .into_iter()
+            .filter(|bundle| bundle.spent().next().is_some())
+            .collect()
+    }
+
+    fn connected_items(&self) -> Vec<SpendBundle<'_>> {
+        let items = self.items();
+        let positions: HashMap<Bytes32, usize> = items
+            .iter()
+            .enumerate()
+            .map(|(i, flat)| (flat.item.coin_id, i))
+            .collect();
+
+        let mut roots: Vec<usize> = (0..items.len()).collect();
+
+        for (i, flat) in items.iter().enumerate() {
+            let parents = [
+                flat.parent_coin_id,
+                flat.origin.map(|origin| origin.parent_coin_id),
+            ];
+            let asserted_by = self.coins_directly_asserted_by(flat.item.coin_id);
+
+            for coin_id in parents.into_iter().flatten().chain(asserted_by) {
+                if let Some(&j) = positions.get(&coin_id) {
+                    union(&mut roots, i, j);
+                }
+            }
+        }
+
+        let mut bundles: Vec<SpendBundle<'_>> = Vec::new();
+        let mut bundle_indices: HashMap<usize, usize> = HashMap::new();
+
+        for (i, flat) in items.iter().enumerate() {
+            let root = find(&mut roots, i);
+            let index = *bundle_indices.entry(root).or_insert_with(|| {
+                bundles.push(SpendBundle { items: Vec::new() });
+                bundles.len() - 1
+            });
+            bundles[index].items.push(flat);
+        }
+
+        bundles
+    }
+}
+
+fn find(roots: &mut [usize], mut i: usize) -> usize {
+    while roots[i] != i {
+        roots[i] = roots[roots[i]];
+        i = roots[i];
+    }
+    i
+}
+
+fn union(roots: &mut [usize], a: usize, b: usize) {
+    let a = find(roots, a);
+    let b = find(roots, b);
+    if a != b {
+        roots[b.max(a)] = a.min(b);
+    }
+}
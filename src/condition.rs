@@ -0,0 +1,888 @@
+use std::fmt;
+
+use chia::protocol::{Bytes, Bytes32, Bytes48};
+use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
+
+/// A condition output by a coin spend.
+///
+/// In the JSON every condition except `CREATE_COIN` carries its arguments as a
+/// list of hex encoded CLVM atoms in `vars`. They are decoded into typed fields
+/// here, and encoded back the same way when serializing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "RawCondition", into = "RawCondition")]
+pub enum Condition {
+    Remark {
+        rest: Vec<Bytes>,
+    },
+    AggSigParent {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    AggSigPuzzle {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    AggSigAmount {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    AggSigPuzzleAmount {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    AggSigParentAmount {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    AggSigParentPuzzle {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    AggSigUnsafe {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    AggSigMe {
+        public_key: Bytes48,
+        message: Bytes,
+    },
+    CreateCoin {
+        puzzle_hash: Bytes32,
+        amount: u64,
+        child_coin_id: Bytes32,
+        address: String,
+    },
+    ReserveFee {
+        amount: u64,
+    },
+    CreateCoinAnnouncement {
+        message: Bytes,
+    },
+    AssertCoinAnnouncement {
+        announcement_id: Bytes32,
+    },
+    CreatePuzzleAnnouncement {
+        message: Bytes,
+    },
+    AssertPuzzleAnnouncement {
+        announcement_id: Bytes32,
+    },
+    AssertConcurrentSpend {
+        coin_id: Bytes32,
+    },
+    AssertConcurrentPuzzle {
+        puzzle_hash: Bytes32,
+    },
+    SendMessage {
+        mode: u8,
+        message: Bytes,
+        args: Vec<Bytes>,
+    },
+    ReceiveMessage {
+        mode: u8,
+        message: Bytes,
+        args: Vec<Bytes>,
+    },
+    AssertMyCoinId {
+        coin_id: Bytes32,
+    },
+    AssertMyParentId {
+        parent_coin_id: Bytes32,
+    },
+    AssertMyPuzzlehash {
+        puzzle_hash: Bytes32,
+    },
+    AssertMyAmount {
+        amount: u64,
+    },
+    AssertMyBirthSeconds {
+        seconds: u64,
+    },
+    AssertMyBirthHeight {
+        height: u32,
+    },
+    AssertEphemeral,
+    AssertSecondsRelative {
+        seconds: Timelock<u64>,
+    },
+    AssertSecondsAbsolute {
+        seconds: Timelock<u64>,
+    },
+    AssertHeightRelative {
+        height: Timelock<u32>,
+    },
+    AssertHeightAbsolute {
+        height: Timelock<u32>,
+    },
+    AssertBeforeSecondsRelative {
+        seconds: Timelock<u64>,
+    },
+    AssertBeforeSecondsAbsolute {
+        seconds: Timelock<u64>,
+    },
+    AssertBeforeHeightRelative {
+        height: Timelock<u32>,
+    },
+    AssertBeforeHeightAbsolute {
+        height: Timelock<u32>,
+    },
+    Softfork {
+        cost: u64,
+        args: Vec<Bytes>,
+    },
+    /// An opcode this crate does not recognize, such as one added by a soft-fork.
+    /// The vars are kept as they are, so the condition can be serialized again.
+    Unknown {
+        opcode: String,
+        vars: Vec<Bytes>,
+    },
+}
+
+impl Condition {
+    /// Decodes a single condition from its JSON value.
+    pub fn from_json(value: serde_json::Value) -> Result<Self, ConditionError> {
+        let raw: RawCondition = serde_json::from_value(value)
+            .map_err(|error| ConditionError::Malformed(error.to_string()))?;
+        raw.try_into()
+    }
+
+    /// The opcode name used in the JSON.
+    pub fn opcode(&self) -> &str {
+        match self {
+            Self::Remark { .. } => "REMARK",
+            Self::AggSigParent { .. } => "AGG_SIG_PARENT",
+            Self::AggSigPuzzle { .. } => "AGG_SIG_PUZZLE",
+            Self::AggSigAmount { .. } => "AGG_SIG_AMOUNT",
+            Self::AggSigPuzzleAmount { .. } => "AGG_SIG_PUZZLE_AMOUNT",
+            Self::AggSigParentAmount { .. } => "AGG_SIG_PARENT_AMOUNT",
+            Self::AggSigParentPuzzle { .. } => "AGG_SIG_PARENT_PUZZLE",
+            Self::AggSigUnsafe { .. } => "AGG_SIG_UNSAFE",
+            Self::AggSigMe { .. } => "AGG_SIG_ME",
+            Self::CreateCoin { .. } => "CREATE_COIN",
+            Self::ReserveFee { .. } => "RESERVE_FEE",
+            Self::CreateCoinAnnouncement { .. } => "CREATE_COIN_ANNOUNCEMENT",
+            Self::AssertCoinAnnouncement { .. } => "ASSERT_COIN_ANNOUNCEMENT",
+            Self::CreatePuzzleAnnouncement { .. } => "CREATE_PUZZLE_ANNOUNCEMENT",
+            Self::AssertPuzzleAnnouncement { .. } => "ASSERT_PUZZLE_ANNOUNCEMENT",
+            Self::AssertConcurrentSpend { .. } => "ASSERT_CONCURRENT_SPEND",
+            Self::AssertConcurrentPuzzle { .. } => "ASSERT_CONCURRENT_PUZZLE",
+            Self::SendMessage { .. } => "SEND_MESSAGE",
+            Self::ReceiveMessage { .. } => "RECEIVE_MESSAGE",
+            Self::AssertMyCoinId { .. } => "ASSERT_MY_COIN_ID",
+            Self::AssertMyParentId { .. } => "ASSERT_MY_PARENT_ID",
+            Self::AssertMyPuzzlehash { .. } => "ASSERT_MY_PUZZLEHASH",
+            Self::AssertMyAmount { .. } => "ASSERT_MY_AMOUNT",
+            Self::AssertMyBirthSeconds { .. } => "ASSERT_MY_BIRTH_SECONDS",
+            Self::AssertMyBirthHeight { .. } => "ASSERT_MY_BIRTH_HEIGHT",
+            Self::AssertEphemeral => "ASSERT_EPHEMERAL",
+            Self::AssertSecondsRelative { .. } => "ASSERT_SECONDS_RELATIVE",
+            Self::AssertSecondsAbsolute { .. } => "ASSERT_SECONDS_ABSOLUTE",
+            Self::AssertHeightRelative { .. } => "ASSERT_HEIGHT_RELATIVE",
+            Self::AssertHeightAbsolute { .. } => "ASSERT_HEIGHT_ABSOLUTE",
+            Self::AssertBeforeSecondsRelative { .. } => "ASSERT_BEFORE_SECONDS_RELATIVE",
+            Self::AssertBeforeSecondsAbsolute { .. } => "ASSERT_BEFORE_SECONDS_ABSOLUTE",
+            Self::AssertBeforeHeightRelative { .. } => "ASSERT_BEFORE_HEIGHT_RELATIVE",
+            Self::AssertBeforeHeightAbsolute { .. } => "ASSERT_BEFORE_HEIGHT_ABSOLUTE",
+            Self::Softfork { .. } => "SOFTFORK",
+            Self::Unknown { opcode, .. } => opcode,
+        }
+    }
+}
+
+/// Why a condition could not be decoded from its JSON representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionError {
+    Malformed(String),
+    MissingField(&'static str),
+    MissingVar {
+        index: usize,
+    },
+    InvalidLength {
+        index: usize,
+        expected: usize,
+        actual: usize,
+    },
+    InvalidInt {
+        index: usize,
+    },
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(message) => write!(f, "{message}"),
+            Self::MissingField(field) => write!(f, "missing field `{field}`"),
+            Self::MissingVar { index } => write!(f, "missing vars[{index}]"),
+            Self::InvalidLength {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "vars[{index}] is {actual} bytes long, expected {expected}"
+            ),
+            Self::InvalidInt { index } => write!(f, "vars[{index}] is not a valid integer"),
+        }
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+/// The condition exactly as it appears in the JSON.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RawCondition {
+    opcode: String,
+
+    #[serde_as(as = "Option<Vec<Hex>>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vars: Option<Vec<Bytes>>,
+
+    #[serde_as(as = "Option<Hex>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    send_puzzle: Option<Bytes32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    amt: Option<u64>,
+
+    #[serde_as(as = "Option<Hex>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    child_coin_name: Option<Bytes32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    send_address: Option<String>,
+}
+
+impl RawCondition {
+    fn new(opcode: &str, vars: Vec<Bytes>) -> Self {
+        Self {
+            opcode: opcode.to_string(),
+            vars: Some(vars),
+            send_puzzle: None,
+            amt: None,
+            child_coin_name: None,
+            send_address: None,
+        }
+    }
+}
+
+/// Positional access to the `vars` of a condition.
+struct Vars<'a>(&'a [Bytes]);
+
+impl Vars<'_> {
+    fn bytes(&self, index: usize) -> Result<Bytes, ConditionError> {
+        self.0
+            .get(index)
+            .cloned()
+            .ok_or(ConditionError::MissingVar { index })
+    }
+
+    fn rest(&self, index: usize) -> Vec<Bytes> {
+        self.0.iter().skip(index).cloned().collect()
+    }
+
+    fn bytes32(&self, index: usize) -> Result<Bytes32, ConditionError> {
+        let bytes = self.bytes(index)?;
+        Bytes32::try_from(bytes.as_ref()).map_err(|_| ConditionError::InvalidLength {
+            index,
+            expected: 32,
+            actual: bytes.len(),
+        })
+    }
+
+    fn bytes48(&self, index: usize) -> Result<Bytes48, ConditionError> {
+        let bytes = self.bytes(index)?;
+        Bytes48::try_from(bytes.as_ref()).map_err(|_| ConditionError::InvalidLength {
+            index,
+            expected: 48,
+            actual: bytes.len(),
+        })
+    }
+
+    fn u64(&self, index: usize) -> Result<u64, ConditionError> {
+        decode_uint(&self.bytes(index)?).ok_or(ConditionError::InvalidInt { index })
+    }
+
+    fn u32(&self, index: usize) -> Result<u32, ConditionError> {
+        u32::try_from(self.u64(index)?).map_err(|_| ConditionError::InvalidInt { index })
+    }
+
+    fn timelock_u64(&self, index: usize) -> Result<Timelock<u64>, ConditionError> {
+        Ok(Timelock::decode(&self.bytes(index)?, 8))
+    }
+
+    fn timelock_u32(&self, index: usize) -> Result<Timelock<u32>, ConditionError> {
+        Ok(Timelock::decode(&self.bytes(index)?, 4).map(|value| value as u32))
+    }
+
+    fn u8(&self, index: usize) -> Result<u8, ConditionError> {
+        u8::try_from(self.u64(index)?).map_err(|_| ConditionError::InvalidInt { index })
+    }
+}
+
+/// The argument of a timelock condition, decoded the way consensus does it.
+///
+/// Consensus doesn't reject a timelock that is negative or too large for the
+/// condition. Depending on the condition, it is either always met or always
+/// fails, so the atom is kept with its sign instead of being malformed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Timelock<T> {
+    Value(T),
+
+    /// A negative atom, kept as it was.
+    Negative(Bytes),
+
+    /// An atom with more significant bytes than the condition allows, kept as it was.
+    Overflow(Bytes),
+}
+
+impl<T> Timelock<T> {
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Self::Value(value) => Some(value),
+            Self::Negative(_) | Self::Overflow(_) => None,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Timelock<U> {
+        match self {
+            Self::Value(value) => Timelock::Value(f(value)),
+            Self::Negative(atom) => Timelock::Negative(atom),
+            Self::Overflow(atom) => Timelock::Overflow(atom),
+        }
+    }
+}
+
+impl Timelock<u64> {
+    /// Decodes an atom allowing at most `max_size` significant bytes, like
+    /// `sanitize_uint` in chia-consensus.
+    fn decode(bytes: &[u8], max_size: usize) -> Self {
+        if bytes.first().is_some_and(|&byte| byte & 0x80 != 0) {
+            return Self::Negative(Bytes::from(bytes));
+        }
+
+        let start = bytes
+            .iter()
+            .position(|&byte| byte != 0)
+            .unwrap_or(bytes.len());
+        if bytes.len() - start > max_size {
+            return Self::Overflow(Bytes::from(bytes));
+        }
+
+        Self::Value(
+            bytes[start..]
+                .iter()
+                .fold(0, |value, &byte| (value << 8) | u64::from(byte)),
+        )
+    }
+
+    fn encode(self) -> Bytes {
+        match self {
+            Self::Value(value) => encode_uint(value),
+            Self::Negative(atom) | Self::Overflow(atom) => atom,
+        }
+    }
+}
+
+/// Decodes a non-negative CLVM integer atom, which is big-endian two's complement.
+pub fn decode_uint(bytes: &[u8]) -> Option<u64> {
+    if bytes.first().is_some_and(|&byte| byte & 0x80 != 0) {
+        return None;
+    }
+
+    let start = bytes
+        .iter()
+        .position(|&byte| byte != 0)
+        .unwrap_or(bytes.len());
+    let significant = &bytes[start..];
+    if significant.len() > 8 {
+        return None;
+    }
+
+    Some(
+        significant
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | u64::from(byte)),
+    )
+}
+
+/// Encodes an integer as the shortest CLVM atom representing it.
+pub fn encode_uint(value: u64) -> Bytes {
+    let bytes = value.to_be_bytes();
+    let start = bytes.iter().position(|&byte| byte != 0).unwrap_or(8);
+    let mut atom = bytes[start..].to_vec();
+    if atom.first().is_some_and(|&byte| byte & 0x80 != 0) {
+        atom.insert(0, 0);
+    }
+    Bytes::new(atom)
+}
+
+impl TryFrom<RawCondition> for Condition {
+    type Error = ConditionError;
+
+    fn try_from(raw: RawCondition) -> Result<Self, Self::Error> {
+        if raw.opcode == "CREATE_COIN" {
+            return Ok(Self::CreateCoin {
+                puzzle_hash: raw
+                    .send_puzzle
+                    .ok_or(ConditionError::MissingField("send_puzzle"))?,
+                amount: raw.amt.ok_or(ConditionError::MissingField("amt"))?,
+                child_coin_id: raw
+                    .child_coin_name
+                    .ok_or(ConditionError::MissingField("child_coin_name"))?,
+                address: raw
+                    .send_address
+                    .ok_or(ConditionError::MissingField("send_address"))?,
+            });
+        }
+
+        let vars = raw.vars.unwrap_or_default();
+        let vars = Vars(&vars);
+
+        Ok(match raw.opcode.as_str() {
+            "REMARK" => Self::Remark { rest: vars.rest(0) },
+            "AGG_SIG_PARENT" => Self::AggSigParent {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "AGG_SIG_PUZZLE" => Self::AggSigPuzzle {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "AGG_SIG_AMOUNT" => Self::AggSigAmount {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "AGG_SIG_PUZZLE_AMOUNT" => Self::AggSigPuzzleAmount {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "AGG_SIG_PARENT_AMOUNT" => Self::AggSigParentAmount {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "AGG_SIG_PARENT_PUZZLE" => Self::AggSigParentPuzzle {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "AGG_SIG_UNSAFE" => Self::AggSigUnsafe {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "AGG_SIG_ME" => Self::AggSigMe {
+                public_key: vars.bytes48(0)?,
+                message: vars.bytes(1)?,
+            },
+            "RESERVE_FEE" => Self::ReserveFee {
+                amount: vars.u64(0)?,
+            },
+            "CREATE_COIN_ANNOUNCEMENT" => Self::CreateCoinAnnouncement {
+                message: vars.bytes(0)?,
+            },
+            "ASSERT_COIN_ANNOUNCEMENT" => Self::AssertCoinAnnouncement {
+                announcement_id: vars.bytes32(0)?,
+            },
+            "CREATE_PUZZLE_ANNOUNCEMENT" => Self::CreatePuzzleAnnouncement {
+                message: vars.bytes(0)?,
+            },
+            "ASSERT_PUZZLE_ANNOUNCEMENT" => Self::AssertPuzzleAnnouncement {
+                announcement_id: vars.bytes32(0)?,
+            },
+            "ASSERT_CONCURRENT_SPEND" => Self::AssertConcurrentSpend {
+                coin_id: vars.bytes32(0)?,
+            },
+            "ASSERT_CONCURRENT_PUZZLE" => Self::AssertConcurrentPuzzle {
+                puzzle_hash: vars.bytes32(0)?,
+            },
+            "SEND_MESSAGE" => Self::SendMessage {
+                mode: vars.u8(0)?,
+                message: vars.bytes(1)?,
+                args: vars.rest(2),
+            },
+            "RECEIVE_MESSAGE" => Self::ReceiveMessage {
+                mode: vars.u8(0)?,
+                message: vars.bytes(1)?,
+                args: vars.rest(2),
+            },
+            "ASSERT_MY_COIN_ID" => Self::AssertMyCoinId {
+                coin_id: vars.bytes32(0)?,
+            },
+            "ASSERT_MY_PARENT_ID" => Self::AssertMyParentId {
+                parent_coin_id: vars.bytes32(0)?,
+            },
+            "ASSERT_MY_PUZZLEHASH" => Self::AssertMyPuzzlehash {
+                puzzle_hash: vars.bytes32(0)?,
+            },
+            "ASSERT_MY_AMOUNT" => Self::AssertMyAmount {
+                amount: vars.u64(0)?,
+            },
+            "ASSERT_MY_BIRTH_SECONDS" => Self::AssertMyBirthSeconds {
+                seconds: vars.u64(0)?,
+            },
+            "ASSERT_MY_BIRTH_HEIGHT" => Self::AssertMyBirthHeight {
+                height: vars.u32(0)?,
+            },
+            "ASSERT_EPHEMERAL" => Self::AssertEphemeral,
+            "ASSERT_SECONDS_RELATIVE" => Self::AssertSecondsRelative {
+                seconds: vars.timelock_u64(0)?,
+            },
+            "ASSERT_SECONDS_ABSOLUTE" => Self::AssertSecondsAbsolute {
+                seconds: vars.timelock_u64(0)?,
+            },
+            "ASSERT_HEIGHT_RELATIVE" => Self::AssertHeightRelative {
+                height: vars.timelock_u32(0)?,
+            },
+            "ASSERT_HEIGHT_ABSOLUTE" => Self::AssertHeightAbsolute {
+                height: vars.timelock_u32(0)?,
+            },
+            "ASSERT_BEFORE_SECONDS_RELATIVE" => Self::AssertBeforeSecondsRelative {
+                seconds: vars.timelock_u64(0)?,
+            },
+            "ASSERT_BEFORE_SECONDS_ABSOLUTE" => Self::AssertBeforeSecondsAbsolute {
+                seconds: vars.timelock_u64(0)?,
+            },
+            "ASSERT_BEFORE_HEIGHT_RELATIVE" => Self::AssertBeforeHeightRelative {
+                height: vars.timelock_u32(0)?,
+            },
+            "ASSERT_BEFORE_HEIGHT_ABSOLUTE" => Self::AssertBeforeHeightAbsolute {
+                height: vars.timelock_u32(0)?,
+            },
+            "SOFTFORK" => Self::Softfork {
+                cost: vars.u64(0)?,
+                args: vars.rest(1),
+            },
+            _ => Self::Unknown {
+                opcode: raw.opcode,
+                vars: vars.rest(0),
+            },
+        })
+    }
+}
+
+impl From<Condition> for RawCondition {
+    fn from(condition: Condition) -> Self {
+        let opcode = condition.opcode().to_string();
+        let vars = match condition {
+            Condition::CreateCoin {
+                puzzle_hash,
+                amount,
+                child_coin_id,
+                address,
+            } => {
+                return Self {
+                    opcode,
+                    vars: None,
+                    send_puzzle: Some(puzzle_hash),
+                    amt: Some(amount),
+                    child_coin_name: Some(child_coin_id),
+                    send_address: Some(address),
+                };
+            }
+            Condition::Remark { rest } => rest,
+            Condition::AggSigParent {
+                public_key,
+                message,
+            }
+            | Condition::AggSigPuzzle {
+                public_key,
+                message,
+            }
+            | Condition::AggSigAmount {
+                public_key,
+                message,
+            }
+            | Condition::AggSigPuzzleAmount {
+                public_key,
+                message,
+            }
+            | Condition::AggSigParentAmount {
+                public_key,
+                message,
+            }
+            | Condition::AggSigParentPuzzle {
+                public_key,
+                message,
+            }
+            | Condition::AggSigUnsafe {
+                public_key,
+                message,
+            }
+            | Condition::AggSigMe {
+                public_key,
+                message,
+            } => vec![public_key.to_vec().into(), message],
+            Condition::CreateCoinAnnouncement { message }
+            | Condition::CreatePuzzleAnnouncement { message } => vec![message],
+            Condition::AssertCoinAnnouncement {
+                announcement_id: id,
+            }
+            | Condition::AssertPuzzleAnnouncement {
+                announcement_id: id,
+            }
+            | Condition::AssertConcurrentSpend { coin_id: id }
+            | Condition::AssertConcurrentPuzzle { puzzle_hash: id }
+            | Condition::AssertMyCoinId { coin_id: id }
+            | Condition::AssertMyParentId { parent_coin_id: id }
+            | Condition::AssertMyPuzzlehash { puzzle_hash: id } => vec![id.to_vec().into()],
+            Condition::SendMessage {
+                mode,
+                message,
+                args,
+            }
+            | Condition::ReceiveMessage {
+                mode,
+                message,
+                args,
+            } => [encode_uint(mode.into()), message]
+                .into_iter()
+                .chain(args)
+                .collect(),
+            Condition::ReserveFee { amount: value }
+            | Condition::AssertMyAmount { amount: value }
+            | Condition::AssertMyBirthSeconds { seconds: value } => {
+                vec![encode_uint(value)]
+            }
+            Condition::AssertMyBirthHeight { height } => vec![encode_uint(height.into())],
+            Condition::AssertSecondsRelative { seconds: value }
+            | Condition::AssertSecondsAbsolute { seconds: value }
+            | Condition::AssertBeforeSecondsRelative { seconds: value }
+            | Condition::AssertBeforeSecondsAbsolute { seconds: value } => vec![value.encode()],
+            Condition::AssertHeightRelative { height }
+            | Condition::AssertHeightAbsolute { height }
+            | Condition::AssertBeforeHeightRelative { height }
+            | Condition::AssertBeforeHeightAbsolute { height } => {
+                vec![height.map(u64::from).encode()]
+            }
+            Condition::AssertEphemeral => Vec::new(),
+            Condition::Softfork { cost, args } => {
+                [encode_uint(cost)].into_iter().chain(args).collect()
+            }
+            Condition::Unknown { vars, .. } => vars,
+        };
+        Self::new(&opcode, vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn decode(opcode: &str, vars: &[&str]) -> Result<Condition, ConditionError> {
+        Condition::from_json(json!({ "opcode": opcode, "vars": vars }))
+    }
+
+    #[test]
+    fn decodes_uints() {
+        assert_eq!(decode_uint(&[]), Some(0));
+        assert_eq!(decode_uint(&[0x7f]), Some(127));
+        assert_eq!(decode_uint(&[0x00, 0x80]), Some(128));
+        assert_eq!(decode_uint(&[0x00, 0x00, 0x00, 0x01]), Some(1));
+        assert_eq!(
+            decode_uint(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Some(u64::MAX)
+        );
+
+        assert_eq!(decode_uint(&[0x80]), None);
+        assert_eq!(decode_uint(&[0xff, 0xff]), None);
+        assert_eq!(decode_uint(&[0x01; 9]), None);
+    }
+
+    #[test]
+    fn encodes_uints() {
+        assert_eq!(encode_uint(0).as_ref(), [0u8; 0]);
+        assert_eq!(encode_uint(127).as_ref(), [0x7f]);
+        assert_eq!(encode_uint(128).as_ref(), [0x00, 0x80]);
+        assert_eq!(encode_uint(0x1234).as_ref(), [0x12, 0x34]);
+        assert_eq!(
+            encode_uint(u64::MAX).as_ref(),
+            [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn decodes_timelocks_like_consensus() {
+        assert_eq!(Timelock::decode(&[], 8), Timelock::Value(0));
+        assert_eq!(Timelock::decode(&[0x00, 0x00, 0x05], 8), Timelock::Value(5));
+        assert_eq!(
+            Timelock::decode(&[0xff], 8),
+            Timelock::Negative(Bytes::from(vec![0xff]))
+        );
+        assert_eq!(
+            Timelock::decode(&[0x01; 9], 8),
+            Timelock::Overflow(Bytes::from(vec![0x01; 9]))
+        );
+
+        assert_eq!(
+            decode("ASSERT_HEIGHT_ABSOLUTE", &["00ffffffff"]),
+            Ok(Condition::AssertHeightAbsolute {
+                height: Timelock::Value(u32::MAX)
+            })
+        );
+        assert_eq!(
+            decode("ASSERT_BEFORE_HEIGHT_RELATIVE", &["0100000000"]),
+            Ok(Condition::AssertBeforeHeightRelative {
+                height: Timelock::Overflow(Bytes::from(vec![0x01, 0, 0, 0, 0]))
+            })
+        );
+        assert_eq!(
+            decode("ASSERT_SECONDS_RELATIVE", &["80"]),
+            Ok(Condition::AssertSecondsRelative {
+                seconds: Timelock::Negative(Bytes::from(vec![0x80]))
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_vars() {
+        assert_eq!(
+            decode("ASSERT_MY_AMOUNT", &[]),
+            Err(ConditionError::MissingVar { index: 0 })
+        );
+        assert_eq!(
+            decode("ASSERT_MY_AMOUNT", &["ff"]),
+            Err(ConditionError::InvalidInt { index: 0 })
+        );
+        assert_eq!(
+            decode("ASSERT_MY_BIRTH_HEIGHT", &["0100000000"]),
+            Err(ConditionError::InvalidInt { index: 0 })
+        );
+        assert_eq!(
+            decode("ASSERT_MY_COIN_ID", &["0101"]),
+            Err(ConditionError::InvalidLength {
+                index: 0,
+                expected: 32,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            Condition::from_json(json!({ "opcode": "CREATE_COIN", "amt": 1 })),
+            Err(ConditionError::MissingField("send_puzzle"))
+        );
+    }
+
+    #[test]
+    fn round_trips_every_variant() {
+        let bytes = |bytes: &[u8]| Bytes::from(bytes.to_vec());
+        let public_key = Bytes48::new([0x11; 48]);
+        let message = bytes(&[0xca, 0xfe]);
+        let id = Bytes32::new([0x22; 32]);
+        let agg_sig = [
+            Condition::AggSigParent {
+                public_key,
+                message: message.clone(),
+            },
+            Condition::AggSigPuzzle {
+                public_key,
+                message: message.clone(),
+            },
+            Condition::AggSigAmount {
+                public_key,
+                message: message.clone(),
+            },
+            Condition::AggSigPuzzleAmount {
+                public_key,
+                message: message.clone(),
+            },
+            Condition::AggSigParentAmount {
+                public_key,
+                message: message.clone(),
+            },
+            Condition::AggSigParentPuzzle {
+                public_key,
+                message: message.clone(),
+            },
+            Condition::AggSigUnsafe {
+                public_key,
+                message: message.clone(),
+            },
+            Condition::AggSigMe {
+                public_key,
+                message: message.clone(),
+            },
+        ];
+        let conditions = agg_sig.into_iter().chain([
+            Condition::Remark {
+                rest: vec![message.clone(), bytes(&[])],
+            },
+            Condition::CreateCoin {
+                puzzle_hash: id,
+                amount: 1000,
+                child_coin_id: Bytes32::new([0x33; 32]),
+                address: "xch1test".to_string(),
+            },
+            Condition::ReserveFee { amount: 128 },
+            Condition::CreateCoinAnnouncement {
+                message: message.clone(),
+            },
+            Condition::AssertCoinAnnouncement {
+                announcement_id: id,
+            },
+            Condition::CreatePuzzleAnnouncement {
+                message: message.clone(),
+            },
+            Condition::AssertPuzzleAnnouncement {
+                announcement_id: id,
+            },
+            Condition::AssertConcurrentSpend { coin_id: id },
+            Condition::AssertConcurrentPuzzle { puzzle_hash: id },
+            Condition::SendMessage {
+                mode: 0x3f,
+                message: message.clone(),
+                args: vec![id.to_vec().into()],
+            },
+            Condition::ReceiveMessage {
+                mode: 0x12,
+                message: message.clone(),
+                args: Vec::new(),
+            },
+            Condition::AssertMyCoinId { coin_id: id },
+            Condition::AssertMyParentId { parent_coin_id: id },
+            Condition::AssertMyPuzzlehash { puzzle_hash: id },
+            Condition::AssertMyAmount { amount: 0 },
+            Condition::AssertMyBirthSeconds { seconds: u64::MAX },
+            Condition::AssertMyBirthHeight { height: u32::MAX },
+            Condition::AssertEphemeral,
+            Condition::AssertSecondsRelative {
+                seconds: Timelock::Value(60),
+            },
+            Condition::AssertSecondsAbsolute {
+                seconds: Timelock::Negative(bytes(&[0xff, 0x00])),
+            },
+            Condition::AssertHeightRelative {
+                height: Timelock::Value(u32::MAX),
+            },
+            Condition::AssertHeightAbsolute {
+                height: Timelock::Overflow(bytes(&[0x01, 0, 0, 0, 0])),
+            },
+            Condition::AssertBeforeSecondsRelative {
+                seconds: Timelock::Overflow(bytes(&[0x01; 9])),
+            },
+            Condition::AssertBeforeSecondsAbsolute {
+                seconds: Timelock::Value(1_700_000_000),
+            },
+            Condition::AssertBeforeHeightRelative {
+                height: Timelock::Negative(bytes(&[0x80])),
+            },
+            Condition::AssertBeforeHeightAbsolute {
+                height: Timelock::Value(0),
+            },
+            Condition::Softfork {
+                cost: 100,
+                args: vec![message.clone()],
+            },
+            Condition::Unknown {
+                opcode: "NEW_OPCODE".to_string(),
+                vars: vec![message.clone(), bytes(&[])],
+            },
+        ]);
+
+        for condition in conditions {
+            let value = serde_json::to_value(&condition).unwrap();
+            assert_eq!(value["opcode"], condition.opcode());
+            assert_eq!(Condition::from_json(value), Ok(condition));
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chia::protocol::Bytes32;
+
+use crate::AnnouncementGraph;
+
+/// A group of coins that all transitively assert each other's announcements,
+/// such as a CAT ring or the legs of an offer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub coins: Vec<Bytes32>,
+
+    /// Every tag carried by a member, sorted and deduplicated.
+    pub tags: Vec<String>,
+}
+
+/// The state of one coin during Tarjan's algorithm.
+struct Visit {
+    index: usize,
+    low_link: usize,
+    on_stack: bool,
+}
+
+impl AnnouncementGraph {
+    /// Splits the coins into strongly connected components, following edges
+    /// from each creator to the coins asserting its announcements. Coins are
+    /// sorted within each component, and components by their first coin.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<Bytes32>> {
+        let mut visits: HashMap<Bytes32, Visit> = HashMap::new();
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+
+        for flat in self.items() {
+            let root = flat.item.coin_id;
+            if visits.contains_key(&root) {
+                continue;
+            }
+
+            // Each frame is a coin and the neighbors it has yet to visit.
+            let mut frames: Vec<(Bytes32, Vec<Bytes32>)> = Vec::new();
+            self.visit(root, &mut visits, &mut stack, &mut frames);
+
+            while let Some((coin_id, neighbors)) = frames.last_mut() {
+                let coin_id = *coin_id;
+
+                if let Some(neighbor) = neighbors.pop() {
+                    match visits.get(&neighbor) {
+                        None => self.visit(neighbor, &mut visits, &mut stack, &mut frames),
+                        Some(visit) if visit.on_stack => {
+                            let index = visit.index;
+                            let visit = visits.get_mut(&coin_id).unwrap();
+                            visit.low_link = visit.low_link.min(index);
+                        }
+                        Some(_) => {}
+                    }
+                    continue;
+                }
+
+                frames.pop();
+                let Visit {
+                    index, low_link, ..
+                } = visits[&coin_id];
+
+                if let Some((parent, _)) = frames.last() {
+                    let parent = visits.get_mut(parent).unwrap();
+                    parent.low_link = parent.low_link.min(low_link);
+                }
+
+                if index == low_link {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        visits.get_mut(&member).unwrap().on_stack = false;
+                        component.push(member);
+                        if member == coin_id {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+
+        components.sort();
+        components
+    }
+
+    /// Returns the components in which coins assert each other in a loop,
+    /// labelled with the tags of their members. A coin asserting its own
+    /// announcement is a cycle on its own.
+    pub fn cycles(&self) -> Vec<Cycle> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|coins| {
+                coins.len() > 1
+                    || self
+                        .coins_directly_asserted_by(coins[0])
+                        .contains(&coins[0])
+            })
+            .map(|coins| {
+                let tags: BTreeSet<String> = coins
+                    .iter()
+                    .filter_map(|&coin_id| self.item(coin_id)?.item.tags.clone())
+                    .flatten()
+                    .collect();
+                Cycle {
+                    coins,
+                    tags: tags.into_iter().collect(),
+                }
+            })
+            .collect()
+    }
+
+    fn visit(
+        &self,
+        coin_id: Bytes32,
+        visits: &mut HashMap<Bytes32, Visit>,
+        stack: &mut Vec<Bytes32>,
+        frames: &mut Vec<(Bytes32, Vec<Bytes32>)>,
+    ) {
+        let index = visits.len();
+        visits.insert(
+            coin_id,
+            Visit {
+                index,
+                low_link: index,
+                on_stack: true,
+            },
+        );
+        stack.push(coin_id);
+
+        let mut neighbors: Vec<Bytes32> = self
+            .coins_directly_asserted_by(coin_id)
+            .into_iter()
+            .collect();
+        neighbors.sort();
+        frames.push((coin_id, neighbors));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chia::protocol::Bytes;
+    use sha2::{digest::FixedOutput, Digest, Sha256};
+
+    use super::*;
+    use crate::{Condition, Item};
+
+    fn coin(i: u32) -> Bytes32 {
+        let mut coin_id = [0; 32];
+        coin_id[28..].copy_from_slice(&i.to_be_bytes());
+        Bytes32::new(coin_id)
+    }
+
+    /// A spent coin that creates one announcement and asserts the ones of `asserts`.
+    fn item(i: u32, asserts: &[u32], tag: &str) -> Item {
+        let mut conditions = vec![Condition::CreateCoinAnnouncement {
+            message: Bytes::from(vec![0x01]),
+        }];
+        for &other in asserts {
+            let mut hasher = Sha256::new();
+            hasher.update(coin(other));
+            hasher.update([0x01]);
+            conditions.push(Condition::AssertCoinAnnouncement {
+                announcement_id: Bytes32::new(hasher.finalize_fixed().into()),
+            });
+        }
+
+        Item {
+            coin_id: coin(i),
+            puzzle_hash: None,
+            amount: None,
+            ty: "standard".to_string(),
+            tags: Some(vec![tag.to_string()]),
+            spend: true,
+            conditions,
+            condition_indices: Vec::new(),
+            path: String::new(),
+            children: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn finds_cycles() {
+        let graph = AnnouncementGraph::new(vec![
+            // 1 -> 2 -> 3 -> 1, with 4 hanging off the ring.
+            item(1, &[3], "ring"),
+            item(2, &[1], "ring"),
+            item(3, &[2], "other"),
+            item(4, &[3], "leaf"),
+            // 5 asserts its own announcement.
+            item(5, &[5], "self"),
+            // 6 -> 7 is no cycle.
+            item(6, &[], "chain"),
+            item(7, &[6], "chain"),
+        ]);
+
+        assert_eq!(
+            graph.strongly_connected_components(),
+            [
+                vec![coin(1), coin(2), coin(3)],
+                vec![coin(4)],
+                vec![coin(5)],
+                vec![coin(6)],
+                vec![coin(7)]
+            ]
+        );
+        assert_eq!(
+            graph.cycles(),
+            [
+                Cycle {
+                    coins: vec![coin(1), coin(2), coin(3)],
+                    tags: vec!["other".to_string(), "ring".to_string()],
+                },
+                Cycle {
+                    coins: vec![coin(5)],
+                    tags: vec!["self".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn handles_long_chains() {
+        // Deep enough to overflow the stack if the search were recursive.
+        let n = 20_000;
+        let items = (0..n)
+            .map(|i| {
+                let asserts = if i == 0 { vec![n - 1] } else { vec![i - 1] };
+                item(i, &asserts, "ring")
+            })
+            .collect();
+        let graph = AnnouncementGraph::new(items);
+
+        let components = graph.strongly_connected_components();
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), n as usize);
+    }
+}
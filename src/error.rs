@@ -0,0 +1,273 @@
+use std::fmt;
+
+use chia::protocol::{Bytes32, Bytes48};
+
+use crate::{AddressError, AnnouncementKey, AnnouncementKind, ConditionError, SelfAssertion};
+
+/// A problem found in a block dump, along with where it was found.
+///
+/// Paths are written as `$[0].Children[1].Conditions[2]`, following the
+/// structure of the JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input is not a JSON list of items.
+    InvalidJson { message: String },
+
+    /// An item could not be decoded, so its conditions and children were skipped.
+    InvalidItem {
+        path: String,
+        coin_id: Option<Bytes32>,
+        message: String,
+    },
+
+    /// A condition could not be decoded, so it was skipped.
+    InvalidCondition {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+        opcode: Option<String>,
+        source: ConditionError,
+    },
+
+    /// The item's `Coin_puzzle_hash` differs from the one its parent created it with.
+    PuzzleHashConflict {
+        path: String,
+        coin_id: Bytes32,
+        explicit: Bytes32,
+        created: Bytes32,
+    },
+
+    /// The item's `Coin_amount` differs from the one its parent created it with.
+    AmountConflict {
+        path: String,
+        coin_id: Bytes32,
+        explicit: u64,
+        created: u64,
+    },
+
+    /// A `CREATE_COIN` names a child coin id that doesn't match its parent,
+    /// puzzle hash and amount.
+    ChildCoinIdMismatch {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+        expected: Bytes32,
+        actual: Bytes32,
+    },
+
+    /// A `CREATE_COIN` has a `send_address` that doesn't encode its puzzle hash.
+    InvalidAddress {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+        address: String,
+        source: AddressError,
+    },
+
+    /// An announcement is asserted, but nothing in the block creates it.
+    UnsatisfiedAssertion {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+        key: AnnouncementKey,
+    },
+
+    /// A `CREATE_PUZZLE_ANNOUNCEMENT` was made by a coin with no known puzzle hash.
+    MissingPuzzleHash {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+    },
+
+    /// A condition asserting the coin's own id, parent, puzzle hash or amount
+    /// doesn't match the item.
+    FailedSelfAssertion {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+        mismatch: SelfAssertion,
+    },
+
+    /// An AGG_SIG condition has a public key that isn't a valid G1 point.
+    InvalidPublicKey {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+        public_key: Bytes48,
+    },
+
+    /// An AGG_SIG condition commits to something about the coin that isn't
+    /// known, so the message it signs can't be built.
+    MissingSignedField {
+        path: String,
+        coin_id: Bytes32,
+        index: usize,
+        opcode: String,
+        field: &'static str,
+    },
+}
+
+impl Error {
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::InvalidJson { .. } => None,
+            Self::InvalidItem { path, .. }
+            | Self::InvalidCondition { path, .. }
+            | Self::PuzzleHashConflict { path, .. }
+            | Self::AmountConflict { path, .. }
+            | Self::ChildCoinIdMismatch { path, .. }
+            | Self::InvalidAddress { path, .. }
+            | Self::UnsatisfiedAssertion { path, .. }
+            | Self::MissingPuzzleHash { path, .. }
+            | Self::FailedSelfAssertion { path, .. }
+            | Self::InvalidPublicKey { path, .. }
+            | Self::MissingSignedField { path, .. } => Some(path),
+        }
+    }
+
+    pub fn coin_id(&self) -> Option<Bytes32> {
+        match self {
+            Self::InvalidJson { .. } => None,
+            Self::InvalidItem { coin_id, .. } => *coin_id,
+            Self::InvalidCondition { coin_id, .. }
+            | Self::PuzzleHashConflict { coin_id, .. }
+            | Self::AmountConflict { coin_id, .. }
+            | Self::ChildCoinIdMismatch { coin_id, .. }
+            | Self::InvalidAddress { coin_id, .. }
+            | Self::UnsatisfiedAssertion { coin_id, .. }
+            | Self::MissingPuzzleHash { coin_id, .. }
+            | Self::FailedSelfAssertion { coin_id, .. }
+            | Self::InvalidPublicKey { coin_id, .. }
+            | Self::MissingSignedField { coin_id, .. } => Some(*coin_id),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidJson { message } => write!(f, "invalid JSON: {message}"),
+            Self::InvalidItem {
+                path,
+                coin_id,
+                message,
+            } => match coin_id {
+                Some(coin_id) => write!(f, "{path}: invalid item {coin_id}: {message}"),
+                None => write!(f, "{path}: invalid item: {message}"),
+            },
+            Self::InvalidCondition {
+                path,
+                coin_id,
+                index,
+                opcode,
+                source,
+            } => {
+                let opcode = opcode.as_deref().unwrap_or("condition");
+                write!(
+                    f,
+                    "{path}: invalid {opcode} at index {index} of coin {coin_id}: {source}"
+                )
+            }
+            Self::PuzzleHashConflict {
+                path,
+                coin_id,
+                explicit,
+                created,
+            } => write!(
+                f,
+                "{path}: coin {coin_id} has puzzle hash {explicit}, \
+                 but was created with {created}"
+            ),
+            Self::AmountConflict {
+                path,
+                coin_id,
+                explicit,
+                created,
+            } => write!(
+                f,
+                "{path}: coin {coin_id} has amount {explicit}, but was created with {created}"
+            ),
+            Self::ChildCoinIdMismatch {
+                path,
+                coin_id,
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{path}: CREATE_COIN at index {index} of coin {coin_id} \
+                 names child {actual}, but creates {expected}"
+            ),
+            Self::InvalidAddress {
+                path,
+                coin_id,
+                index,
+                address,
+                source,
+            } => write!(
+                f,
+                "{path}: CREATE_COIN at index {index} of coin {coin_id} \
+                 sends to {address}: {source}"
+            ),
+            Self::UnsatisfiedAssertion {
+                path,
+                coin_id,
+                index,
+                key,
+            } => {
+                let opcode = match key.kind {
+                    AnnouncementKind::Coin => "ASSERT_COIN_ANNOUNCEMENT",
+                    AnnouncementKind::Puzzle => "ASSERT_PUZZLE_ANNOUNCEMENT",
+                };
+                write!(
+                    f,
+                    "{path}: {opcode} at index {index} of coin {coin_id} \
+                     asserts {}, which nothing in the block creates",
+                    key.announcement_id
+                )
+            }
+            Self::MissingPuzzleHash {
+                path,
+                coin_id,
+                index,
+            } => write!(
+                f,
+                "{path}: CREATE_PUZZLE_ANNOUNCEMENT at index {index} of coin {coin_id} \
+                 needs a puzzle hash, but the coin has none"
+            ),
+            Self::FailedSelfAssertion {
+                path,
+                coin_id,
+                index,
+                mismatch,
+            } => write!(
+                f,
+                "{path}: {} at index {index} of coin {coin_id} {mismatch}",
+                mismatch.opcode()
+            ),
+            Self::InvalidPublicKey {
+                path,
+                coin_id,
+                index,
+                public_key,
+            } => write!(
+                f,
+                "{path}: AGG_SIG at index {index} of coin {coin_id} \
+                 has invalid public key {public_key}"
+            ),
+            Self::MissingSignedField {
+                path,
+                coin_id,
+                index,
+                opcode,
+                field,
+            } => write!(
+                f,
+                "{path}: {opcode} at index {index} of coin {coin_id} \
+                 signs the coin's {field}, which isn't known"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,267 @@
+use std::{collections::HashSet, fmt::Write};
+
+use chia::protocol::{Bytes, Bytes32};
+use serde::{Deserialize, Serialize};
+use serde_with::{hex::Hex, serde_as};
+
+use crate::{AnnouncementGraph, AnnouncementKind};
+
+/// The graph in a form meant for other tools, serialized by
+/// [`AnnouncementGraph::to_json`] as `{ "nodes": [...], "edges": [...] }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphExport {
+    pub nodes: Vec<NodeExport>,
+    pub edges: Vec<EdgeExport>,
+}
+
+/// A coin. Ids and hashes are hex encoded without a `0x` prefix.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeExport {
+    /// The coin id.
+    #[serde_as(as = "Hex")]
+    pub id: Bytes32,
+
+    /// The `Type` of the item, such as `token` or `standard`.
+    #[serde(rename = "type")]
+    pub ty: String,
+
+    pub tags: Vec<String>,
+
+    /// The puzzle hash, or `null` if it isn't known.
+    #[serde_as(as = "Option<Hex>")]
+    pub puzzle_hash: Option<Bytes32>,
+
+    /// The amount in mojos, or `null` if it isn't known.
+    pub amount: Option<u64>,
+
+    /// Whether the coin is spent in the block.
+    pub spent: bool,
+
+    /// Whether the coin was selected as a root of the report.
+    pub root: bool,
+
+    /// How deeply the item was nested in the `Children` tree.
+    pub depth: usize,
+}
+
+/// An edge between two coins.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeExport {
+    pub kind: EdgeKind,
+
+    /// The creator of the announcement, or the parent of the coin.
+    #[serde_as(as = "Hex")]
+    pub from: Bytes32,
+
+    /// The coin asserting the announcement, or the coin created.
+    #[serde_as(as = "Hex")]
+    pub to: Bytes32,
+
+    /// The announcement id, or `null` for `create_coin` edges.
+    #[serde_as(as = "Option<Hex>")]
+    pub announcement_id: Option<Bytes32>,
+
+    /// The announced message, or `null` for `create_coin` edges.
+    #[serde_as(as = "Option<Hex>")]
+    pub message: Option<Bytes>,
+
+    /// The index of the creating condition in the `from` coin, if known.
+    pub from_condition: Option<usize>,
+
+    /// The index of the asserting condition in the `to` coin, if any.
+    pub to_condition: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    CoinAnnouncement,
+    PuzzleAnnouncement,
+    CreateCoin,
+}
+
+/// The first few hex digits of an id, enough to tell coins apart in a diagram.
+fn short(id: Bytes32) -> String {
+    hex::encode(&id[..4])
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn node_lines(node: &NodeExport) -> Vec<String> {
+    let mut lines = vec![short(node.id), node.ty.clone()];
+    if !node.tags.is_empty() {
+        lines.push(node.tags.join(", "));
+    }
+    if let Some(puzzle_hash) = node.puzzle_hash {
+        lines.push(format!("ph {}", short(puzzle_hash)));
+    }
+    if let Some(amount) = node.amount {
+        lines.push(format!("{amount} mojos"));
+    }
+    lines.push(if node.spent { "spent" } else { "unspent" }.to_string());
+    lines
+}
+
+impl AnnouncementGraph {
+    /// Collects the nodes and edges shared by every export format. Edges
+    /// point from the creator of an announcement to the coin asserting it, or
+    /// from a parent to the child it creates.
+    pub fn export(&self, roots: &HashSet<Bytes32>) -> GraphExport {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+
+        for flat in self.items() {
+            let item = &flat.item;
+            nodes.push(NodeExport {
+                id: item.coin_id,
+                ty: item.ty.clone(),
+                tags: item.tags.clone().unwrap_or_default(),
+                puzzle_hash: item.puzzle_hash,
+                amount: item.amount,
+                spent: item.spend,
+                root: roots.contains(&item.coin_id),
+                depth: flat.depth,
+            });
+        }
+
+        for flat in self.items() {
+            let parent = flat
+                .origin
+                .map(|origin| (origin.parent_coin_id, Some(origin.index)))
+                .or(flat
+                    .parent_coin_id
+                    .map(|parent_coin_id| (parent_coin_id, None)));
+
+            if let Some((parent_coin_id, index)) = parent {
+                edges.push(EdgeExport {
+                    kind: EdgeKind::CreateCoin,
+                    from: parent_coin_id,
+                    to: flat.item.coin_id,
+                    announcement_id: None,
+                    message: None,
+                    from_condition: index,
+                    to_condition: None,
+                });
+            }
+
+            for edge in self.edges_from(flat.item.coin_id) {
+                edges.push(EdgeExport {
+                    kind: match edge.key.kind {
+                        AnnouncementKind::Coin => EdgeKind::CoinAnnouncement,
+                        AnnouncementKind::Puzzle => EdgeKind::PuzzleAnnouncement,
+                    },
+                    from: edge.creator.coin_id,
+                    to: edge.asserter.coin_id,
+                    announcement_id: Some(edge.key.announcement_id),
+                    message: Some(edge.message),
+                    from_condition: Some(edge.creator.index),
+                    to_condition: Some(edge.asserter.index),
+                });
+            }
+        }
+
+        GraphExport { nodes, edges }
+    }
+
+    /// Renders the graph in Graphviz DOT format, with the given roots highlighted.
+    pub fn to_dot(&self, roots: &HashSet<Bytes32>) -> String {
+        let export = self.export(roots);
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph announcements {{").unwrap();
+        writeln!(dot, "  node [shape=box, fontname=monospace];").unwrap();
+
+        for node in export.nodes.iter() {
+            let label = node_lines(node)
+                .iter()
+                .map(|line| escape_dot(line))
+                .collect::<Vec<_>>()
+                .join("\\n");
+            let style = if node.root {
+                ", style=\"filled,bold\", fillcolor=gold"
+            } else if !node.spent {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            writeln!(dot, "  \"{}\" [label=\"{label}\"{style}];", node.id).unwrap();
+        }
+
+        for edge in export.edges.iter() {
+            let (color, style, label) = match (edge.kind, edge.announcement_id) {
+                (EdgeKind::CoinAnnouncement, Some(id)) => {
+                    ("blue", "solid", format!("coin {}", short(id)))
+                }
+                (EdgeKind::PuzzleAnnouncement, Some(id)) => {
+                    ("darkgreen", "dashed", format!("puzzle {}", short(id)))
+                }
+                _ => ("gray", "dotted", "CREATE_COIN".to_string()),
+            };
+            writeln!(
+                dot,
+                "  \"{}\" -> \"{}\" [color={color}, style={style}, label=\"{label}\"];",
+                edge.from, edge.to
+            )
+            .unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    /// Renders the graph as a Mermaid flowchart, with the given roots highlighted.
+    /// Announcement edges are labelled with the full announcement id and message.
+    pub fn to_mermaid(&self, roots: &HashSet<Bytes32>) -> String {
+        let export = self.export(roots);
+
+        let mut mermaid = String::new();
+        writeln!(mermaid, "flowchart LR").unwrap();
+        writeln!(mermaid, "  classDef root fill:#ffd700,stroke-width:3px").unwrap();
+        writeln!(mermaid, "  classDef unspent stroke-dasharray:4").unwrap();
+
+        for node in export.nodes.iter() {
+            let label = node_lines(node).join("<br/>").replace('"', "#quot;");
+            let class = if node.root {
+                ":::root"
+            } else if !node.spent {
+                ":::unspent"
+            } else {
+                ""
+            };
+            writeln!(mermaid, "  c{}[\"{label}\"]{class}", node.id).unwrap();
+        }
+
+        for edge in export.edges.iter() {
+            match (edge.kind, edge.announcement_id, &edge.message) {
+                (EdgeKind::CreateCoin, ..) | (_, None, _) | (_, _, None) => {
+                    writeln!(mermaid, "  c{} -.->|CREATE_COIN| c{}", edge.from, edge.to).unwrap();
+                }
+                (kind, Some(id), Some(message)) => {
+                    let (arrow, kind) = match kind {
+                        EdgeKind::PuzzleAnnouncement => ("==>", "puzzle"),
+                        _ => ("-->", "coin"),
+                    };
+                    writeln!(
+                        mermaid,
+                        "  c{} {arrow}|\"{kind} {id}<br/>message {}\"| c{}",
+                        edge.from,
+                        hex::encode(message),
+                        edge.to
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        mermaid
+    }
+
+    /// Serializes the graph as JSON, following the schema of [`GraphExport`].
+    pub fn to_json(&self, roots: &HashSet<Bytes32>) -> String {
+        serde_json::to_string_pretty(&self.export(roots)).expect("graph export is serializable")
+    }
+}
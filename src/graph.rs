@@ -0,0 +1,268 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
+use chia::protocol::{Bytes, Bytes32};
+
+use crate::{
+    flatten_items, resolve_origins, signature_requirements, verify_addresses, verify_assertions,
+    verify_child_coin_ids, verify_self_assertions, AnnouncementKey, Announcements, Condition,
+    ConditionRef, Error, FlatItem, Item, Network, SignatureRequirement,
+};
+
+/// A condition whose opcode was not recognized, and where it was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownCondition<'a> {
+    pub coin_id: Bytes32,
+    pub index: usize,
+    pub opcode: &'a str,
+}
+
+/// One announcement binding two coins: the creator makes it, and the asserter
+/// asserts it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnouncementEdge {
+    pub key: AnnouncementKey,
+    pub message: Bytes,
+    pub creator: ConditionRef,
+    pub asserter: ConditionRef,
+}
+
+/// Settings used while building an [`AnnouncementGraph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphOptions {
+    /// The bech32m prefix addresses are checked against and generated with.
+    pub address_prefix: String,
+
+    /// The network AGG_SIG conditions are signed for.
+    pub network: Network,
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        Self {
+            address_prefix: "xch".to_string(),
+            network: Network::Mainnet,
+        }
+    }
+}
+
+/// The coins of a block and the announcements that bind them together.
+///
+/// A coin is "asserted by" another coin when the other coin asserts an
+/// announcement that the first one created.
+#[derive(Debug, Clone)]
+pub struct AnnouncementGraph {
+    items: Vec<FlatItem>,
+    index: HashMap<Bytes32, usize>,
+    announcements: Announcements,
+    signatures: Vec<SignatureRequirement>,
+    signature_errors: Vec<Error>,
+    errors: Vec<Error>,
+    options: GraphOptions,
+}
+
+impl AnnouncementGraph {
+    pub fn new(items: Vec<Item>) -> Self {
+        Self::with_options(items, GraphOptions::default())
+    }
+
+    pub fn with_options(items: Vec<Item>, options: GraphOptions) -> Self {
+        let mut items = flatten_items(items);
+        let mut errors = resolve_origins(&mut items);
+        errors.extend(verify_child_coin_ids(&items));
+        errors.extend(verify_addresses(&items, &options.address_prefix));
+        let (announcements, announcement_errors) = Announcements::from_items(&items);
+        errors.extend(announcement_errors);
+        errors.extend(verify_assertions(&items, &announcements));
+        errors.extend(verify_self_assertions(&items));
+        let (signatures, signature_errors) = signature_requirements(&items, options.network);
+        let index = items
+            .iter()
+            .enumerate()
+            .map(|(i, flat)| (flat.item.coin_id, i))
+            .collect();
+
+        Self {
+            items,
+            index,
+            announcements,
+            signatures,
+            signature_errors,
+            errors,
+            options,
+        }
+    }
+
+    pub fn options(&self) -> &GraphOptions {
+        &self.options
+    }
+
+    pub fn items(&self) -> &[FlatItem] {
+        &self.items
+    }
+
+    pub fn item(&self, coin_id: Bytes32) -> Option<&FlatItem> {
+        self.index.get(&coin_id).map(|&i| &self.items[i])
+    }
+
+    pub fn announcements(&self) -> &Announcements {
+        &self.announcements
+    }
+
+    /// Every signature the spent coins require, in the order of their conditions.
+    pub fn signature_requirements(&self) -> &[SignatureRequirement] {
+        &self.signatures
+    }
+
+    /// AGG_SIG conditions whose requirement couldn't be built, because the
+    /// public key is invalid or the coin details it signs aren't known.
+    pub fn signature_errors(&self) -> &[Error] {
+        &self.signature_errors
+    }
+
+    /// Problems found while building the graph, such as announcements that
+    /// had to be skipped.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Returns every condition with an opcode this crate does not recognize.
+    pub fn unknown_conditions(&self) -> Vec<UnknownCondition<'_>> {
+        let mut unknown = Vec::new();
+        for flat in self.items.iter() {
+            for (index, condition) in flat.item.indexed_conditions() {
+                if let Condition::Unknown { opcode, .. } = condition {
+                    unknown.push(UnknownCondition {
+                        coin_id: flat.item.coin_id,
+                        index,
+                        opcode,
+                    });
+                }
+            }
+        }
+        unknown
+    }
+
+    /// Returns every coin that transitively asserts an announcement of the given coin.
+    pub fn coins_asserted_by(&self, coin_id: Bytes32) -> HashSet<Bytes32> {
+        let mut coins = HashSet::new();
+        let mut stack = vec![coin_id];
+        while let Some(coin_id) = stack.pop() {
+            for asserted in self.coins_directly_asserted_by(coin_id) {
+                if coins.insert(asserted) {
+                    stack.push(asserted);
+                }
+            }
+        }
+        coins
+    }
+
+    /// Returns every coin the given coin depends on, meaning the coins that
+    /// transitively create announcements it asserts. These must be spent
+    /// alongside it.
+    pub fn coins_asserting(&self, coin_id: Bytes32) -> HashSet<Bytes32> {
+        let mut coins = HashSet::new();
+        let mut stack = vec![coin_id];
+        while let Some(coin_id) = stack.pop() {
+            for asserting in self.coins_directly_asserting(coin_id) {
+                if coins.insert(asserting) {
+                    stack.push(asserting);
+                }
+            }
+        }
+        coins
+    }
+
+    /// An alias of [`AnnouncementGraph::coins_asserting`].
+    pub fn dependencies_of(&self, coin_id: Bytes32) -> HashSet<Bytes32> {
+        self.coins_asserting(coin_id)
+    }
+
+    /// Returns the coins that assert an announcement created by the given coin.
+    pub fn coins_directly_asserted_by(&self, coin_id: Bytes32) -> HashSet<Bytes32> {
+        let announcements = &self.announcements;
+        announcements
+            .created
+            .get(&coin_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|key| announcements.asserters.get(key))
+            .flatten()
+            .map(|asserter| asserter.coin_id)
+            .collect()
+    }
+
+    /// Returns the coins that created an announcement asserted by the given coin.
+    pub fn coins_directly_asserting(&self, coin_id: Bytes32) -> HashSet<Bytes32> {
+        let announcements = &self.announcements;
+        announcements
+            .asserted
+            .get(&coin_id)
+            .into_iter()
+            .flatten()
+            .flat_map(|&key| announcements.creators(key))
+            .collect()
+    }
+
+    /// Returns an edge for every assertion of an announcement created by the given coin.
+    pub fn edges_from(&self, coin_id: Bytes32) -> Vec<AnnouncementEdge> {
+        let announcements = &self.announcements;
+        let mut keys = announcements
+            .created
+            .get(&coin_id)
+            .cloned()
+            .unwrap_or_default();
+        keys.sort();
+        keys.dedup();
+
+        let mut edges = Vec::new();
+        for key in keys {
+            let asserters = announcements.asserters.get(&key).into_iter().flatten();
+            for (creator, message) in announcements.creations(key) {
+                if creator.coin_id != coin_id {
+                    continue;
+                }
+                for &asserter in asserters.clone() {
+                    edges.push(AnnouncementEdge {
+                        key,
+                        message: message.clone(),
+                        creator,
+                        asserter,
+                    });
+                }
+            }
+        }
+        edges
+    }
+
+    /// Finds the shortest chain of announcements leading from one coin to
+    /// another, where each edge goes from a creator to an asserter. Returns
+    /// `None` if the second coin isn't asserted by the first.
+    pub fn path(&self, from: Bytes32, to: Bytes32) -> Option<Vec<AnnouncementEdge>> {
+        let mut reached: HashMap<Bytes32, Option<AnnouncementEdge>> = HashMap::new();
+        reached.insert(from, None);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(coin_id) = queue.pop_front() {
+            if coin_id == to {
+                let mut path = Vec::new();
+                let mut current = to;
+                while let Some(Some(edge)) = reached.get(&current) {
+                    current = edge.creator.coin_id;
+                    path.push(edge.clone());
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for edge in self.edges_from(coin_id) {
+                let next = edge.asserter.coin_id;
+                if let Entry::Vacant(entry) = reached.entry(next) {
+                    entry.insert(Some(edge));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,290 @@
+use chia::protocol::Bytes32;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::{hex::Hex, serde_as};
+
+use crate::{encode_address, Condition, Error};
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Item {
+    #[serde(rename = "Coin")]
+    #[serde_as(as = "Hex")]
+    pub coin_id: Bytes32,
+
+    #[serde(rename = "Coin_puzzle_hash")]
+    #[serde_as(as = "Option<Hex>")]
+    pub puzzle_hash: Option<Bytes32>,
+
+    #[serde(
+        rename = "Coin_amount",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub amount: Option<u64>,
+
+    #[serde(rename = "Type")]
+    pub ty: String,
+
+    #[serde(rename = "Tags")]
+    pub tags: Option<Vec<String>>,
+
+    #[serde(rename = "Spend")]
+    pub spend: bool,
+
+    #[serde(rename = "Conditions")]
+    pub conditions: Vec<Condition>,
+
+    /// The position of each condition in the JSON `Conditions` list, which is
+    /// further along than its position in `conditions` if one before it could
+    /// not be decoded. Positions that aren't recorded match `conditions`.
+    #[serde(skip)]
+    pub condition_indices: Vec<usize>,
+
+    /// The JSON path the item was parsed from, such as `$[1].Children[0]`,
+    /// which can skip positions if items before it could not be decoded.
+    /// Empty if the item wasn't parsed from JSON.
+    #[serde(skip)]
+    pub path: String,
+
+    #[serde(rename = "Children")]
+    pub children: Vec<Item>,
+}
+
+impl Item {
+    /// The conditions along with their positions in the JSON `Conditions` list.
+    pub fn indexed_conditions(&self) -> impl Iterator<Item = (usize, &Condition)> {
+        self.conditions.iter().enumerate().map(|(i, condition)| {
+            let index = self.condition_indices.get(i).copied().unwrap_or(i);
+            (index, condition)
+        })
+    }
+
+    /// The address of the coin's puzzle hash, if it is known.
+    pub fn address(&self, prefix: &str) -> Option<String> {
+        encode_address(self.puzzle_hash?, prefix).ok()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .as_ref()
+            .is_some_and(|tags| tags.iter().any(|item_tag| item_tag == tag))
+    }
+
+    /// Whether the coin is a CAT, whose amount and outputs are in the CAT's
+    /// own units rather than mojos of XCH.
+    pub fn is_cat(&self) -> bool {
+        self.ty == "token" || self.has_tag("cat_v1") || self.has_tag("cat_v2")
+    }
+}
+
+/// The fields of an item, with the conditions and children left undecoded.
+#[serde_as]
+#[derive(Deserialize)]
+struct RawItem {
+    #[serde(rename = "Coin")]
+    #[serde_as(as = "Hex")]
+    coin_id: Bytes32,
+
+    #[serde(rename = "Coin_puzzle_hash")]
+    #[serde_as(as = "Option<Hex>")]
+    puzzle_hash: Option<Bytes32>,
+
+    #[serde(rename = "Coin_amount", default)]
+    amount: Option<u64>,
+
+    #[serde(rename = "Type")]
+    ty: String,
+
+    #[serde(rename = "Tags")]
+    tags: Option<Vec<String>>,
+
+    #[serde(rename = "Spend")]
+    spend: bool,
+
+    #[serde(rename = "Conditions")]
+    conditions: Vec<Value>,
+
+    #[serde(rename = "Children")]
+    children: Vec<Value>,
+}
+
+/// An item lifted out of the `Children` tree, with its position recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatItem {
+    pub item: Item,
+    pub parent_coin_id: Option<Bytes32>,
+    pub depth: usize,
+    pub path: String,
+
+    /// The `CREATE_COIN` that produced this coin, once resolved.
+    pub origin: Option<Origin>,
+}
+
+/// Points at a `CREATE_COIN` condition of another item in the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Origin {
+    pub parent_coin_id: Bytes32,
+    pub index: usize,
+}
+
+/// Parses the top level items of a block dump, failing on the first problem.
+pub fn parse_items(json: &str) -> Result<Vec<Item>, Box<Error>> {
+    let (items, mut errors) = parse_items_lenient(json);
+    if errors.is_empty() {
+        Ok(items)
+    } else {
+        Err(Box::new(errors.remove(0)))
+    }
+}
+
+/// Parses the top level items of a block dump, skipping anything that can't be
+/// decoded and returning every problem found alongside the items.
+pub fn parse_items_lenient(json: &str) -> (Vec<Item>, Vec<Error>) {
+    let mut errors = Vec::new();
+
+    let values: Vec<Value> = match serde_json::from_str(json) {
+        Ok(values) => values,
+        Err(error) => {
+            errors.push(Error::InvalidJson {
+                message: error.to_string(),
+            });
+            return (Vec::new(), errors);
+        }
+    };
+
+    let items = values
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, value)| parse_item(value, format!("$[{i}]"), &mut errors))
+        .collect();
+
+    (items, errors)
+}
+
+fn parse_item(value: Value, path: String, errors: &mut Vec<Error>) -> Option<Item> {
+    let coin_id = value
+        .get("Coin")
+        .and_then(Value::as_str)
+        .and_then(|coin_id| hex::decode(coin_id).ok())
+        .and_then(|coin_id| Bytes32::try_from(coin_id).ok());
+
+    let raw: RawItem = match serde_json::from_value(value) {
+        Ok(raw) => raw,
+        Err(error) => {
+            errors.push(Error::InvalidItem {
+                path,
+                coin_id,
+                message: error.to_string(),
+            });
+            return None;
+        }
+    };
+
+    let mut conditions = Vec::new();
+    let mut condition_indices = Vec::new();
+    for (index, value) in raw.conditions.into_iter().enumerate() {
+        let opcode = value
+            .get("opcode")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        match Condition::from_json(value) {
+            Ok(condition) => {
+                conditions.push(condition);
+                condition_indices.push(index);
+            }
+            Err(source) => errors.push(Error::InvalidCondition {
+                path: format!("{path}.Conditions[{index}]"),
+                coin_id: raw.coin_id,
+                index,
+                opcode,
+                source,
+            }),
+        }
+    }
+
+    let children = raw
+        .children
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, value)| parse_item(value, format!("{path}.Children[{i}]"), errors))
+        .collect();
+
+    Some(Item {
+        coin_id: raw.coin_id,
+        puzzle_hash: raw.puzzle_hash,
+        amount: raw.amount,
+        ty: raw.ty,
+        tags: raw.tags,
+        spend: raw.spend,
+        conditions,
+        condition_indices,
+        path,
+        children,
+    })
+}
+
+/// Flattens every level of the `Children` tree in depth-first order.
+/// The `children` of each returned item are left empty. Items keep the path
+/// they were parsed from, and the rest are given one from their position.
+pub fn flatten_items(items: Vec<Item>) -> Vec<FlatItem> {
+    let mut flat = Vec::new();
+    let mut stack: Vec<(Item, Option<Bytes32>, usize, String)> = items
+        .into_iter()
+        .enumerate()
+        .rev()
+        .map(|(i, item)| (item, None, 0, format!("$[{i}]")))
+        .collect();
+
+    while let Some((mut item, parent_coin_id, depth, position)) = stack.pop() {
+        let path = if item.path.is_empty() {
+            position
+        } else {
+            item.path.clone()
+        };
+        let children = std::mem::take(&mut item.children);
+        for (i, child) in children.into_iter().enumerate().rev() {
+            let child_path = format!("{path}.Children[{i}]");
+            stack.push((child, Some(item.coin_id), depth + 1, child_path));
+        }
+        flat.push(FlatItem {
+            item,
+            parent_coin_id,
+            depth,
+            path,
+            origin: None,
+        });
+    }
+
+    flat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_paths_past_invalid_items() {
+        let json = r#"[
+            {"Coin": "zz"},
+            {"Coin": "0101010101010101010101010101010101010101010101010101010101010101",
+             "Coin_puzzle_hash": null, "Type": "standard", "Tags": null, "Spend": true,
+             "Conditions": [{"opcode": "ASSERT_MY_AMOUNT", "vars": []}, {"opcode": "REMARK", "vars": []}],
+             "Children": [
+                {"Coin": "zz"},
+                {"Coin": "0202020202020202020202020202020202020202020202020202020202020202",
+                 "Coin_puzzle_hash": null, "Type": "standard", "Tags": null, "Spend": false,
+                 "Conditions": [], "Children": []}
+             ]}
+        ]"#;
+        let (items, errors) = parse_items_lenient(json);
+        assert_eq!(errors.len(), 3);
+
+        let flat = flatten_items(items);
+        let paths: Vec<_> = flat.iter().map(|flat| flat.path.as_str()).collect();
+        assert_eq!(paths, ["$[1]", "$[1].Children[1]"]);
+        let indices: Vec<_> = flat[0].item.indexed_conditions().map(|(i, _)| i).collect();
+        assert_eq!(indices, [1]);
+    }
+}
@@ -0,0 +1,29 @@
+mod address;
+mod announcements;
+mod balance;
+mod bundle;
+mod condition;
+mod cycles;
+mod error;
+mod export;
+mod graph;
+mod item;
+mod resolve;
+mod roots;
+mod signatures;
+mod validate;
+
+pub use address::*;
+pub use announcements::*;
+pub use balance::*;
+pub use bundle::*;
+pub use condition::*;
+pub use cycles::*;
+pub use error::*;
+pub use export::*;
+pub use graph::*;
+pub use item::*;
+pub use resolve::*;
+pub use roots::*;
+pub use signatures::*;
+pub use validate::*;
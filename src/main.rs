@@ -1,249 +1,469 @@
 use std::{
-    collections::{HashMap, HashSet},
     fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
 };
 
-use chia::protocol::{Bytes, Bytes32};
-use serde::{Deserialize, Serialize};
-use serde_with::{hex::Hex, serde_as};
-use sha2::{digest::FixedOutput, Digest, Sha256};
+use anyhow::{anyhow, bail};
+use chia::{bls::Signature, protocol::Bytes32};
+use clap::{Parser, ValueEnum};
+use conds::{
+    parse_items, parse_items_lenient, AnnouncementGraph, AnnouncementKind, Balance, Error,
+    FlatItem, GraphOptions, Network, RootSelector, SignatureCheck, SpendBundle,
+};
 
-#[serde_as]
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-struct Item {
-    #[serde(rename = "Coin")]
-    #[serde_as(as = "Hex")]
-    coin_id: Bytes32,
+/// Finds which coins in a block are bound together by announcements.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Block JSON files to read, or `-` for stdin. Each one is reported on separately.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Report on coins with this tag. Every kind of selection given must
+    /// match, and if none are given, coins tagged `settlement_payments` are used.
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+
+    /// Report on coins of this type, such as `token` or `standard`.
+    #[arg(long = "type")]
+    types: Vec<String>,
+
+    /// Report on the coin with this id.
+    #[arg(long = "coin", value_parser = parse_bytes32)]
+    coins: Vec<Bytes32>,
+
+    /// Report on coins with this puzzle hash.
+    #[arg(long = "puzzle-hash", value_parser = parse_bytes32)]
+    puzzle_hashes: Vec<Bytes32>,
+
+    /// Only report on coins spent in the block.
+    #[arg(long, conflicts_with = "unspent")]
+    spent: bool,
+
+    /// Only report on coins not spent in the block.
+    #[arg(long)]
+    unspent: bool,
+
+    /// What to report for each selected coin.
+    #[arg(long, value_enum, default_value_t = Query::AssertedBy)]
+    query: Query,
+
+    /// Explain the chain of announcements linking each selected coin to this coin.
+    #[arg(long, value_parser = parse_bytes32)]
+    path_to: Option<Bytes32>,
+
+    /// The network the block is from, `mainnet` or `testnet11`, which
+    /// determines the messages AGG_SIG conditions sign.
+    #[arg(long, default_value_t = Network::Mainnet)]
+    network: Network,
+
+    /// The address prefix to check and print addresses with. Defaults to the
+    /// prefix of the network, `xch` or `txch`.
+    #[arg(long)]
+    prefix: Option<String>,
+
+    /// The aggregated signature of the spend bundle, in hex, to check against
+    /// the signatures its AGG_SIG conditions require.
+    #[arg(long, value_parser = parse_signature)]
+    signature: Option<Signature>,
+
+    /// Fail on the first malformed record, unrecognized opcode or failed
+    /// check, instead of listing it in the report. Signatures that can't be
+    /// built are still only listed.
+    #[arg(long)]
+    strict: bool,
+
+    /// How to write the report.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Write the report to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
 
-    #[serde(rename = "Coin_puzzle_hash")]
-    #[serde_as(as = "Option<Hex>")]
-    puzzle_hash: Option<Bytes32>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Query {
+    /// The coins that transitively assert announcements of the coin.
+    AssertedBy,
 
-    #[serde(rename = "Type")]
-    ty: String,
+    /// The coins whose announcements the coin transitively asserts, which
+    /// must be spent alongside it.
+    Dependencies,
+}
 
-    #[serde(rename = "Tags")]
-    tags: Option<Vec<String>>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// A plain text report.
+    Text,
 
-    #[serde(rename = "Spend")]
-    spend: bool,
+    /// A Graphviz DOT graph of the coins and announcements, with the selected
+    /// coins highlighted. Diagnostics are written to stderr instead.
+    Dot,
 
-    #[serde(rename = "Conditions")]
-    conditions: Vec<Condition>,
+    /// A Mermaid flowchart, like the DOT graph. Only one input can be written.
+    Mermaid,
 
-    #[serde(rename = "Children")]
-    children: Vec<Item>,
+    /// A JSON document of nodes and edges, like the DOT graph. Only one input
+    /// can be written.
+    Json,
 }
 
-#[serde_as]
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(tag = "opcode", rename_all = "SCREAMING_SNAKE_CASE")]
-enum Condition {
-    CreatePuzzleAnnouncement {
-        #[serde_as(as = "Vec<Hex>")]
-        vars: Vec<Bytes>,
-    },
-    CreateCoinAnnouncement {
-        #[serde_as(as = "Vec<Hex>")]
-        vars: Vec<Bytes>,
-    },
-    AssertPuzzleAnnouncement {
-        #[serde_as(as = "Vec<Hex>")]
-        vars: Vec<Bytes32>,
-    },
-    AssertCoinAnnouncement {
-        #[serde_as(as = "Vec<Hex>")]
-        vars: Vec<Bytes32>,
-    },
-    CreateCoin {
-        #[serde_as(as = "Hex")]
-        #[serde(rename = "send_puzzle")]
-        puzzle_hash: Bytes32,
-
-        #[serde(rename = "amt")]
-        amount: u64,
-
-        #[serde_as(as = "Hex")]
-        #[serde(rename = "child_coin_name")]
-        child_coin_id: Bytes32,
-
-        #[serde(rename = "send_address")]
-        address: String,
-    },
-    AssertMyCoinId {
-        #[serde_as(as = "Vec<Hex>")]
-        vars: Vec<Bytes32>,
-    },
-    AggSigMe {
-        #[serde_as(as = "Vec<Hex>")]
-        vars: Vec<Bytes>,
-    },
-    ReserveFee {
-        #[serde_as(as = "Vec<Hex>")]
-        vars: Vec<Bytes>,
-    },
+fn parse_bytes32(value: &str) -> anyhow::Result<Bytes32> {
+    let bytes = hex::decode(value.strip_prefix("0x").unwrap_or(value))?;
+    Ok(Bytes32::try_from(bytes)?)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct CreateCoinAnnouncement {
-    coin_id: Bytes32,
-    message: Bytes,
-    announcement_id: Bytes32,
+fn parse_signature(value: &str) -> anyhow::Result<Signature> {
+    let bytes = hex::decode(value.strip_prefix("0x").unwrap_or(value))?;
+    let bytes: [u8; 96] = bytes.try_into().map_err(|_| anyhow!("expected 96 bytes"))?;
+    Ok(Signature::from_bytes(&bytes)?)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct CreatePuzzleAnnouncement {
-    coin_id: Bytes32,
-    puzzle_hash: Bytes32,
-    message: Bytes,
-    announcement_id: Bytes32,
+fn read_input(input: &Path) -> anyhow::Result<String> {
+    if input.as_os_str() == "-" {
+        let mut file = String::new();
+        io::stdin().read_to_string(&mut file)?;
+        Ok(file)
+    } else {
+        Ok(fs::read_to_string(input)?)
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct AssertPuzzleAnnouncement {
-    coin_id: Bytes32,
-    announcement_id: Bytes32,
-}
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    if matches!(args.format, Format::Mermaid | Format::Json) && args.inputs.len() > 1 {
+        let format = args
+            .format
+            .to_possible_value()
+            .expect("formats aren't skipped");
+        bail!("Only one input can be written as {}", format.get_name());
+    }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct AssertCoinAnnouncement {
-    coin_id: Bytes32,
-    announcement_id: Bytes32,
-}
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(fs::File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Announcements {
-    create_coin: HashMap<Bytes32, CreateCoinAnnouncement>,
-    create_puzzle: HashMap<Bytes32, CreatePuzzleAnnouncement>,
-    assert_puzzle: Vec<AssertPuzzleAnnouncement>,
-    assert_coin: Vec<AssertCoinAnnouncement>,
-}
+    for input in args.inputs.iter() {
+        let source = input.display();
+        let file = read_input(input)?;
+
+        let (items, mut diagnostics) = if args.strict {
+            let items = parse_items(&file).map_err(|error| anyhow!("{source}: {error}"))?;
+            (items, Vec::new())
+        } else {
+            parse_items_lenient(&file)
+        };
+
+        let options = GraphOptions {
+            address_prefix: args
+                .prefix
+                .clone()
+                .unwrap_or_else(|| args.network.address_prefix().to_string()),
+            network: args.network,
+        };
+        let graph = AnnouncementGraph::with_options(items, options);
+
+        if args.strict {
+            if let Some(error) = graph.errors().first() {
+                bail!("{source}: {error}");
+            }
+            if let Some(condition) = graph.unknown_conditions().first() {
+                bail!(
+                    "{source}: Unknown opcode {} in condition {} of coin {}",
+                    condition.opcode,
+                    condition.index,
+                    condition.coin_id
+                );
+            }
+        }
+        diagnostics.extend(graph.errors().iter().cloned());
+        diagnostics.extend(graph.signature_errors().iter().cloned());
 
-fn main() -> anyhow::Result<()> {
-    let file = fs::read_to_string("block.json")?;
-    let mut items: Vec<Item> = serde_json::from_str(&file)?;
-
-    let mut create_coin_announcements = HashMap::<Bytes32, CreateCoinAnnouncement>::new();
-    let mut create_puzzle_announcements = HashMap::<Bytes32, CreatePuzzleAnnouncement>::new();
-    let mut assert_puzzle_announcements = vec![];
-    let mut assert_coin_announcements = vec![];
-
-    for item in items.clone() {
-        items.extend(item.children);
-    }
-
-    for item in items.clone() {
-        for condition in item.conditions {
-            match condition {
-                Condition::CreateCoinAnnouncement { vars } => {
-                    let message = vars[0].clone();
-
-                    let mut hasher = Sha256::new();
-                    hasher.update(item.coin_id);
-                    hasher.update(&message);
-
-                    let announcement_id = Bytes32::new(hasher.finalize_fixed().into());
-
-                    create_coin_announcements.insert(
-                        announcement_id,
-                        CreateCoinAnnouncement {
-                            coin_id: item.coin_id,
-                            message,
-                            announcement_id,
-                        },
-                    );
-                }
-                Condition::CreatePuzzleAnnouncement { vars } => {
-                    let message = vars[0].clone();
-
-                    let mut hasher = Sha256::new();
-                    hasher.update(item.puzzle_hash.unwrap());
-                    hasher.update(&message);
-
-                    let announcement_id = Bytes32::new(hasher.finalize_fixed().into());
-
-                    create_puzzle_announcements.insert(
-                        announcement_id,
-                        CreatePuzzleAnnouncement {
-                            coin_id: item.coin_id,
-                            puzzle_hash: item.puzzle_hash.unwrap(),
-                            message,
-                            announcement_id,
-                        },
-                    );
+        match args.format {
+            Format::Text => {
+                if args.inputs.len() > 1 {
+                    writeln!(output, "== {source}")?;
                 }
-                Condition::AssertCoinAnnouncement { vars } => {
-                    assert_coin_announcements.push(AssertCoinAnnouncement {
-                        coin_id: item.coin_id,
-                        announcement_id: vars[0],
-                    });
-                }
-                Condition::AssertPuzzleAnnouncement { vars } => {
-                    assert_puzzle_announcements.push(AssertPuzzleAnnouncement {
-                        coin_id: item.coin_id,
-                        announcement_id: vars[0],
-                    });
+                report(&args, &graph, &diagnostics, &mut output)?;
+            }
+            Format::Dot | Format::Mermaid | Format::Json => {
+                let roots = graph
+                    .roots(&root_selector(&args))
+                    .into_iter()
+                    .map(|flat| flat.item.coin_id)
+                    .collect();
+                let rendered = match args.format {
+                    Format::Mermaid => graph.to_mermaid(&roots),
+                    Format::Json => graph.to_json(&roots),
+                    _ => graph.to_dot(&roots),
+                };
+                writeln!(output, "{}", rendered.trim_end())?;
+                for diagnostic in diagnostics {
+                    eprintln!("{source}: {diagnostic}");
                 }
-                _ => {}
             }
         }
     }
 
-    let announcements = Announcements {
-        create_coin: create_coin_announcements,
-        create_puzzle: create_puzzle_announcements,
-        assert_puzzle: assert_puzzle_announcements,
-        assert_coin: assert_coin_announcements,
+    Ok(())
+}
+
+/// The coins selected for reporting on the command line.
+fn root_selector(args: &Args) -> RootSelector {
+    let selector = RootSelector {
+        tags: args.tags.clone(),
+        types: args.types.clone(),
+        coin_ids: args.coins.clone(),
+        puzzle_hashes: args.puzzle_hashes.clone(),
+        spent: match (args.spent, args.unspent) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
     };
 
-    for item in items {
-        if item
-            .tags
-            .unwrap_or_default()
-            .contains(&"settlement_payments".to_string())
-        {
-            let coins = coins_asserted_by(item.coin_id, &announcements);
-            println!("Coin {} is asserted by {:?}", item.coin_id, coins);
-        }
+    if selector.is_empty() {
+        RootSelector::settlement_payments()
+    } else {
+        selector
     }
+}
+
+/// Writes the announcements linking two coins, in whichever direction they are linked.
+fn report_path(
+    graph: &AnnouncementGraph,
+    coin_id: Bytes32,
+    target: Bytes32,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let (from, to) = (coin_id, target);
+    let Some((from, to, path)) = graph
+        .path(from, to)
+        .map(|path| (from, to, path))
+        .or_else(|| graph.path(to, from).map(|path| (to, from, path)))
+    else {
+        return writeln!(output, "  No announcement path links it to {target}");
+    };
 
+    writeln!(output, "  Path from {from} to {to}:")?;
+    for edge in path {
+        writeln!(
+            output,
+            "    {}[{}] creates {} announcement {} with message {}, asserted by {}[{}]",
+            edge.creator.coin_id,
+            edge.creator.index,
+            edge.key.kind,
+            edge.key.announcement_id,
+            hex::encode(&edge.message),
+            edge.asserter.coin_id,
+            edge.asserter.index
+        )?;
+    }
     Ok(())
 }
 
-fn coins_asserted_by(coin_id: Bytes32, announcements: &Announcements) -> HashSet<Bytes32> {
-    let mut coins = HashSet::new();
-    let mut stack = vec![coin_id];
-    while let Some(coin_id) = stack.pop() {
-        for asserted in coins_directly_asserted_by(coin_id, announcements) {
-            if coins.insert(asserted) {
-                stack.push(asserted);
-            }
+/// Writes whether the aggregated signature matches the block, or failing
+/// that, which coins or bundle it would match.
+fn report_signature(
+    graph: &AnnouncementGraph,
+    bundles: &[SpendBundle<'_>],
+    signature: &Signature,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    let suspects = match graph.verify_signature(signature) {
+        SignatureCheck::Valid => {
+            return writeln!(
+                output,
+                "Aggregated signature covers all {} required signatures",
+                graph.signature_requirements().len()
+            );
         }
+        SignatureCheck::Invalid { suspects } => suspects,
+    };
+
+    if suspects.is_empty() {
+        writeln!(
+            output,
+            "Aggregated signature does not verify, and leaving out coins does not explain it"
+        )?;
+    } else {
+        writeln!(
+            output,
+            "Aggregated signature does not verify, but would without the requirements of {suspects:?}"
+        )?;
     }
-    coins
-}
 
-fn coins_directly_asserted_by(coin_id: Bytes32, announcements: &Announcements) -> HashSet<Bytes32> {
-    let mut coins = HashSet::new();
-    for created in announcements.create_coin.values() {
-        if created.coin_id != coin_id {
+    for (i, bundle) in bundles.iter().enumerate() {
+        if graph.signatures_required_by(bundle).is_empty() {
             continue;
         }
-        for asserted in announcements.assert_coin.iter() {
-            if created.announcement_id == asserted.announcement_id {
-                coins.insert(asserted.coin_id);
-            }
+        if graph.bundle_signature_matches(bundle, signature) {
+            writeln!(output, "Aggregated signature matches bundle {i} alone")?;
         }
     }
-    for created in announcements.create_puzzle.values() {
-        if created.coin_id != coin_id {
-            continue;
+    Ok(())
+}
+
+fn format_balance(balance: &Balance) -> String {
+    let fee = match balance.implied_fee() {
+        Some(fee) => fee.to_string(),
+        None => format!(
+            "unknown ({} spent coins of unknown amount)",
+            balance.unknown_amounts
+        ),
+    };
+    let cats = match balance.cat_spends {
+        0 => String::new(),
+        cat_spends => format!(", leaving out {cat_spends} CAT spends"),
+    };
+    format!(
+        "XCH inputs {}, outputs {}, reserved fee {} vs implied fee {fee}{cats}",
+        balance.inputs, balance.outputs, balance.reserved_fee
+    )
+}
+
+fn report(
+    args: &Args,
+    graph: &AnnouncementGraph,
+    diagnostics: &[Error],
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    for FlatItem {
+        item,
+        parent_coin_id,
+        depth,
+        ..
+    } in graph.roots(&root_selector(args))
+    {
+        let (relation, coins) = match args.query {
+            Query::AssertedBy => ("is asserted by", graph.coins_asserted_by(item.coin_id)),
+            Query::Dependencies => ("depends on", graph.coins_asserting(item.coin_id)),
+        };
+        let position = match parent_coin_id {
+            Some(parent_coin_id) => format!("depth {depth}, parent {parent_coin_id}"),
+            None => format!("depth {depth}"),
+        };
+        let address = item
+            .address(&graph.options().address_prefix)
+            .map(|address| format!(", {address}"))
+            .unwrap_or_default();
+        writeln!(
+            output,
+            "Coin {} ({position}{address}) {relation} {:?}",
+            item.coin_id, coins
+        )?;
+
+        if let Some(target) = args.path_to {
+            report_path(graph, item.coin_id, target, output)?;
         }
-        for asserted in announcements.assert_puzzle.iter() {
-            if created.announcement_id == asserted.announcement_id {
-                coins.insert(asserted.coin_id);
-            }
+    }
+
+    let bundles = graph.spend_bundles();
+    writeln!(output, "{} spend bundles", bundles.len())?;
+    for (i, bundle) in bundles.iter().enumerate() {
+        writeln!(
+            output,
+            "Bundle {i} spends {} of {} coins: {:?}",
+            bundle.spent().count(),
+            bundle.items.len(),
+            bundle.coin_ids()
+        )?;
+        let balance = bundle.balance();
+        writeln!(output, "  {}", format_balance(&balance))?;
+        if let Some(shortfall) = balance.fee_shortfall() {
+            writeln!(
+                output,
+                "  Warning: bundle {i} reserves {shortfall} mojos more fee than it leaves by itself"
+            )?;
+        }
+        let signatures = graph.signatures_required_by(bundle);
+        if !signatures.is_empty() {
+            writeln!(output, "  Requires {} signatures", signatures.len())?;
         }
+        for signature in signatures {
+            writeln!(
+                output,
+                "    {}[{}] {} by {} of {}",
+                signature.coin_id,
+                signature.index,
+                signature.opcode,
+                hex::encode(signature.public_key.to_bytes()),
+                hex::encode(&signature.signed_message)
+            )?;
+        }
+    }
+    if let Some(signature) = &args.signature {
+        report_signature(graph, &bundles, signature, output)?;
+    }
+
+    let balance = graph.balance();
+    writeln!(output, "Block {}", format_balance(&balance))?;
+    if let Some(shortfall) = balance.fee_shortfall() {
+        writeln!(
+            output,
+            "Block reserves {shortfall} mojos more fee than it leaves, so it is invalid"
+        )?;
+    }
+
+    for cycle in graph.cycles() {
+        writeln!(
+            output,
+            "Cycle of {} coins tagged {:?}: {:?}",
+            cycle.coins.len(),
+            cycle.tags,
+            cycle.coins
+        )?;
+    }
+
+    for condition in graph.unknown_conditions() {
+        writeln!(
+            output,
+            "Coin {} has unknown opcode {} in condition {}",
+            condition.coin_id, condition.opcode, condition.index
+        )?;
     }
-    coins
+
+    let orphans = graph.announcements().orphans();
+    for orphan in orphans.iter() {
+        writeln!(
+            output,
+            "Coin {} creates {} announcement {} with message {} that nothing asserts",
+            orphan.coin_id,
+            orphan.key.kind,
+            orphan.key.announcement_id,
+            hex::encode(&orphan.message)
+        )?;
+    }
+    let coin_orphans = orphans
+        .iter()
+        .filter(|orphan| orphan.key.kind == AnnouncementKind::Coin)
+        .count();
+    writeln!(
+        output,
+        "{} orphan announcements ({coin_orphans} coin, {} puzzle)",
+        orphans.len(),
+        orphans.len() - coin_orphans
+    )?;
+
+    for duplicate in graph.announcements().duplicates() {
+        let asserted = if duplicate.asserted {
+            "asserted"
+        } else {
+            "unasserted"
+        };
+        writeln!(
+            output,
+            "Duplicate {asserted} {} announcement {} is created by {:?}",
+            duplicate.key.kind, duplicate.key.announcement_id, duplicate.creators
+        )?;
+    }
+
+    for diagnostic in diagnostics {
+        writeln!(output, "{diagnostic}")?;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::{Condition, Error, FlatItem, Origin};
+
+/// Links every item to the `CREATE_COIN` that produced it, if that condition is
+/// in the block, and fills in the puzzle hash and amount it gives the coin.
+///
+/// Values already present on the item are kept, and reported if they disagree.
+pub fn resolve_origins(items: &mut [FlatItem]) -> Vec<Error> {
+    let mut created = HashMap::new();
+    for FlatItem { item, .. } in items.iter() {
+        for (index, condition) in item.indexed_conditions() {
+            if let Condition::CreateCoin {
+                puzzle_hash,
+                amount,
+                child_coin_id,
+                ..
+            } = condition
+            {
+                let origin = Origin {
+                    parent_coin_id: item.coin_id,
+                    index,
+                };
+                created.insert(*child_coin_id, (origin, *puzzle_hash, *amount));
+            }
+        }
+    }
+
+    let mut errors = Vec::new();
+
+    for flat in items.iter_mut() {
+        let item = &mut flat.item;
+        let Some(&(origin, puzzle_hash, amount)) = created.get(&item.coin_id) else {
+            continue;
+        };
+        flat.origin = Some(origin);
+
+        match item.puzzle_hash {
+            None => item.puzzle_hash = Some(puzzle_hash),
+            Some(explicit) if explicit != puzzle_hash => {
+                errors.push(Error::PuzzleHashConflict {
+                    path: flat.path.clone(),
+                    coin_id: item.coin_id,
+                    explicit,
+                    created: puzzle_hash,
+                });
+            }
+            Some(_) => {}
+        }
+
+        match item.amount {
+            None => item.amount = Some(amount),
+            Some(explicit) if explicit != amount => {
+                errors.push(Error::AmountConflict {
+                    path: flat.path.clone(),
+                    coin_id: item.coin_id,
+                    explicit,
+                    created: amount,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,61 @@
+use chia::protocol::Bytes32;
+
+use crate::{AnnouncementGraph, FlatItem, Item};
+
+/// Chooses which coins a report is about.
+///
+/// Every criterion that is set must match, and a list criterion matches if any
+/// of its values does. For example, tags `cat_v2` and `settlement_payments`
+/// together with `spent: Some(false)` selects unspent coins carrying either tag.
+/// A selector with no criteria matches nothing.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RootSelector {
+    pub tags: Vec<String>,
+    pub types: Vec<String>,
+    pub coin_ids: Vec<Bytes32>,
+    pub puzzle_hashes: Vec<Bytes32>,
+    pub spent: Option<bool>,
+}
+
+impl RootSelector {
+    /// Selects the offer settlement coins, which is what reports were originally about.
+    pub fn settlement_payments() -> Self {
+        Self {
+            tags: vec!["settlement_payments".to_string()],
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+            && self.types.is_empty()
+            && self.coin_ids.is_empty()
+            && self.puzzle_hashes.is_empty()
+            && self.spent.is_none()
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        (self.tags.is_empty() || self.tags.iter().any(|tag| item.has_tag(tag)))
+            && (self.types.is_empty() || self.types.contains(&item.ty))
+            && (self.coin_ids.is_empty() || self.coin_ids.contains(&item.coin_id))
+            && (self.puzzle_hashes.is_empty()
+                || item
+                    .puzzle_hash
+                    .is_some_and(|puzzle_hash| self.puzzle_hashes.contains(&puzzle_hash)))
+            && self.spent.is_none_or(|spent| spent == item.spend)
+    }
+}
+
+impl AnnouncementGraph {
+    /// Returns the items matching the selector, in the order of [`AnnouncementGraph::items`].
+    pub fn roots(&self, selector: &RootSelector) -> Vec<&FlatItem> {
+        self.items()
+            .iter()
+            .filter(|flat| selector.matches(&flat.item))
+            .collect()
+    }
+}
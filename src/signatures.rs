@@ -0,0 +1,599 @@
+use std::{fmt, str::FromStr};
+
+use chia::{
+    bls::{aggregate_verify, hash_to_g2, GTElement, PublicKey, Signature},
+    protocol::{Bytes, Bytes32},
+};
+use sha2::{digest::FixedOutput, Digest, Sha256};
+
+use crate::{encode_uint, AnnouncementGraph, Condition, Error, FlatItem, SpendBundle};
+
+/// The genesis challenge of mainnet, `ccd5bb71…0e5fbb`.
+const MAINNET_GENESIS_CHALLENGE: [u8; 32] = [
+    0xcc, 0xd5, 0xbb, 0x71, 0x18, 0x35, 0x32, 0xbf, 0xf2, 0x20, 0xba, 0x46, 0xc2, 0x68, 0x99, 0x1a,
+    0x3f, 0xf0, 0x7e, 0xb3, 0x58, 0xe8, 0x25, 0x5a, 0x65, 0xc3, 0x0a, 0x2d, 0xce, 0x0e, 0x5f, 0xbb,
+];
+
+/// The genesis challenge of testnet11, `37a90eb5…236615`.
+const TESTNET11_GENESIS_CHALLENGE: [u8; 32] = [
+    0x37, 0xa9, 0x0e, 0xb5, 0x18, 0x5a, 0x9c, 0x44, 0x39, 0xa9, 0x1d, 0xdc, 0x98, 0xbb, 0xad, 0xce,
+    0x7b, 0x4f, 0xeb, 0xa0, 0x60, 0xd5, 0x01, 0x16, 0xa0, 0x67, 0xde, 0x66, 0xbf, 0x23, 0x66, 0x15,
+];
+
+/// The chain a block is from, which determines what AGG_SIG conditions sign.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet11,
+}
+
+impl Network {
+    pub fn genesis_challenge(self) -> Bytes32 {
+        Bytes32::new(match self {
+            Self::Mainnet => MAINNET_GENESIS_CHALLENGE,
+            Self::Testnet11 => TESTNET11_GENESIS_CHALLENGE,
+        })
+    }
+
+    /// The usual address prefix on this network.
+    pub fn address_prefix(self) -> &'static str {
+        match self {
+            Self::Mainnet => "xch",
+            Self::Testnet11 => "txch",
+        }
+    }
+
+    /// The data appended to the message of an AGG_SIG condition with the
+    /// given opcode. `AGG_SIG_ME` uses the genesis challenge itself, and the
+    /// newer conditions hash the opcode into it, so a signature for one kind
+    /// of condition can't be replayed for another.
+    pub fn additional_data(self, opcode: u8) -> Bytes32 {
+        let genesis_challenge = self.genesis_challenge();
+        if opcode == AGG_SIG_ME {
+            return genesis_challenge;
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(genesis_challenge);
+        hasher.update([opcode]);
+        Bytes32::new(hasher.finalize_fixed().into())
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mainnet => write!(f, "mainnet"),
+            Self::Testnet11 => write!(f, "testnet11"),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mainnet" => Ok(Self::Mainnet),
+            "testnet11" => Ok(Self::Testnet11),
+            _ => Err(format!(
+                "unknown network `{value}`, expected `mainnet` or `testnet11`"
+            )),
+        }
+    }
+}
+
+const AGG_SIG_PARENT: u8 = 43;
+const AGG_SIG_PUZZLE: u8 = 44;
+const AGG_SIG_AMOUNT: u8 = 45;
+const AGG_SIG_PUZZLE_AMOUNT: u8 = 46;
+const AGG_SIG_PARENT_AMOUNT: u8 = 47;
+const AGG_SIG_PARENT_PUZZLE: u8 = 48;
+const AGG_SIG_UNSAFE: u8 = 49;
+const AGG_SIG_ME: u8 = 50;
+
+/// A signature that must be part of the aggregated signature of the spend
+/// bundle for an AGG_SIG condition to pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureRequirement {
+    pub coin_id: Bytes32,
+    pub index: usize,
+    pub opcode: String,
+    pub public_key: PublicKey,
+
+    /// The message in the condition.
+    pub message: Bytes,
+
+    /// What the key actually signs: the message followed by the coin details
+    /// the condition commits to and the network's additional data.
+    pub signed_message: Bytes,
+}
+
+/// The parts of a coin an AGG_SIG condition can commit to.
+enum CoinField {
+    Parent,
+    PuzzleHash,
+    Amount,
+    CoinId,
+}
+
+impl CoinField {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Parent => "parent coin id",
+            Self::PuzzleHash => "puzzle hash",
+            Self::Amount => "amount",
+            Self::CoinId => "coin id",
+        }
+    }
+}
+
+/// Decodes every AGG_SIG condition of the spent items into the public key and
+/// the full message it must sign on the given network. Conditions with an
+/// invalid public key, or committing to something unknown about the coin, are
+/// skipped and reported.
+pub fn signature_requirements(
+    items: &[FlatItem],
+    network: Network,
+) -> (Vec<SignatureRequirement>, Vec<Error>) {
+    let mut requirements = Vec::new();
+    let mut errors = Vec::new();
+
+    for flat in items.iter().filter(|flat| flat.item.spend) {
+        let item = &flat.item;
+        for (index, condition) in item.indexed_conditions() {
+            let (opcode, public_key, message, fields): (_, _, _, &[CoinField]) = match condition {
+                Condition::AggSigParent {
+                    public_key,
+                    message,
+                } => (AGG_SIG_PARENT, public_key, message, &[CoinField::Parent]),
+                Condition::AggSigPuzzle {
+                    public_key,
+                    message,
+                } => (
+                    AGG_SIG_PUZZLE,
+                    public_key,
+                    message,
+                    &[CoinField::PuzzleHash],
+                ),
+                Condition::AggSigAmount {
+                    public_key,
+                    message,
+                } => (AGG_SIG_AMOUNT, public_key, message, &[CoinField::Amount]),
+                Condition::AggSigPuzzleAmount {
+                    public_key,
+                    message,
+                } => (
+                    AGG_SIG_PUZZLE_AMOUNT,
+                    public_key,
+                    message,
+                    &[CoinField::PuzzleHash, CoinField::Amount],
+                ),
+                Condition::AggSigParentAmount {
+                    public_key,
+                    message,
+                } => (
+                    AGG_SIG_PARENT_AMOUNT,
+                    public_key,
+                    message,
+                    &[CoinField::Parent, CoinField::Amount],
+                ),
+                Condition::AggSigParentPuzzle {
+                    public_key,
+                    message,
+                } => (
+                    AGG_SIG_PARENT_PUZZLE,
+                    public_key,
+                    message,
+                    &[CoinField::Parent, CoinField::PuzzleHash],
+                ),
+                Condition::AggSigUnsafe {
+                    public_key,
+                    message,
+                } => (AGG_SIG_UNSAFE, public_key, message, &[]),
+                Condition::AggSigMe {
+                    public_key,
+                    message,
+                } => (AGG_SIG_ME, public_key, message, &[CoinField::CoinId]),
+                _ => continue,
+            };
+            let path = format!("{}.Conditions[{index}]", flat.path);
+
+            let Ok(public_key) = PublicKey::from_bytes(&public_key.to_bytes()) else {
+                errors.push(Error::InvalidPublicKey {
+                    path,
+                    coin_id: item.coin_id,
+                    index,
+                    public_key: *public_key,
+                });
+                continue;
+            };
+
+            let mut signed_message = message.to_vec();
+            let mut missing = None;
+            for field in fields {
+                let bytes = match field {
+                    CoinField::Parent => flat
+                        .origin
+                        .map(|origin| origin.parent_coin_id)
+                        .or(flat.parent_coin_id)
+                        .map(|parent_coin_id| parent_coin_id.to_vec()),
+                    CoinField::PuzzleHash => item.puzzle_hash.map(|ph| ph.to_vec()),
+                    CoinField::Amount => item.amount.map(|amount| encode_uint(amount).to_vec()),
+                    CoinField::CoinId => Some(item.coin_id.to_vec()),
+                };
+                match bytes {
+                    Some(bytes) => signed_message.extend(bytes),
+                    None => {
+                        missing = Some(field.name());
+                        break;
+                    }
+                }
+            }
+            if let Some(field) = missing {
+                errors.push(Error::MissingSignedField {
+                    path,
+                    coin_id: item.coin_id,
+                    index,
+                    opcode: condition.opcode().to_string(),
+                    field,
+                });
+                continue;
+            }
+
+            if opcode != AGG_SIG_UNSAFE {
+                signed_message.extend_from_slice(&network.additional_data(opcode));
+            }
+
+            requirements.push(SignatureRequirement {
+                coin_id: item.coin_id,
+                index,
+                opcode: condition.opcode().to_string(),
+                public_key,
+                message: message.clone(),
+                signed_message: Bytes::new(signed_message),
+            });
+        }
+    }
+
+    (requirements, errors)
+}
+
+/// The outcome of checking an aggregated signature against the signatures
+/// it should combine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureCheck {
+    Valid,
+
+    /// The signature doesn't match. The suspects are the fewest coins it would
+    /// match without, which usually means their requirements were never
+    /// signed. It's empty if no such set of coins was found, such as when a
+    /// signed message was changed.
+    Invalid {
+        suspects: Vec<Bytes32>,
+    },
+}
+
+/// Whether an aggregated signature combines exactly the given requirements,
+/// without looking for the coins it leaves out when it doesn't.
+pub fn signature_matches(requirements: &[&SignatureRequirement], signature: &Signature) -> bool {
+    let pairs = requirements
+        .iter()
+        .map(|requirement| (&requirement.public_key, requirement.signed_message.as_ref()));
+    aggregate_verify(signature, pairs)
+}
+
+/// How many sets of coins [`verify_aggregated_signature`] tries leaving out
+/// before giving up on finding the suspects.
+const MAX_SUSPECT_SETS: usize = 1 << 16;
+
+/// Checks an aggregated signature against the given requirements.
+///
+/// On failure, it looks for the smallest set of coins the signature matches
+/// without, by leaving out every single coin, then every pair, and so on.
+/// Each coin's requirements are paired once up front, so trying a set only
+/// takes a few multiplications in the target group rather than new pairings.
+pub fn verify_aggregated_signature(
+    requirements: &[&SignatureRequirement],
+    signature: &Signature,
+) -> SignatureCheck {
+    if signature_matches(requirements, signature) {
+        return SignatureCheck::Valid;
+    }
+
+    // The product of the pairings of each coin's requirements, which is what
+    // the coin contributes to the aggregate when it is signed correctly.
+    let mut coins: Vec<(Bytes32, GTElement)> = Vec::new();
+    for requirement in requirements {
+        let mut augmented = requirement.public_key.to_bytes().to_vec();
+        augmented.extend_from_slice(&requirement.signed_message);
+        let paired = hash_to_g2(&augmented).pair(&requirement.public_key);
+
+        match coins
+            .iter_mut()
+            .find(|(coin_id, _)| *coin_id == requirement.coin_id)
+        {
+            Some((_, product)) => *product *= &paired,
+            None => coins.push((requirement.coin_id, paired)),
+        }
+    }
+
+    let Some(total) = coins
+        .iter()
+        .map(|(_, paired)| paired.clone())
+        .reduce(|total, paired| &total * &paired)
+    else {
+        return SignatureCheck::Invalid {
+            suspects: Vec::new(),
+        };
+    };
+    let signed = signature.pair(&PublicKey::generator());
+
+    // The signature matches every coin but the left out ones when pairing it
+    // and multiplying in their contributions gives the total.
+    let mut tried = 0;
+    for size in 1..=coins.len() {
+        let mut left_out: Vec<usize> = (0..size).collect();
+        loop {
+            if tried == MAX_SUSPECT_SETS {
+                return SignatureCheck::Invalid {
+                    suspects: Vec::new(),
+                };
+            }
+            tried += 1;
+
+            let mut product = signed.clone();
+            for &i in left_out.iter() {
+                product *= &coins[i].1;
+            }
+            if product == total {
+                let suspects = left_out.iter().map(|&i| coins[i].0).collect();
+                return SignatureCheck::Invalid { suspects };
+            }
+
+            // Move on to the next set of the same size, in lexicographic order.
+            let Some(i) = (0..size)
+                .rev()
+                .find(|&i| left_out[i] != i + coins.len() - size)
+            else {
+                break;
+            };
+            left_out[i] += 1;
+            for j in i + 1..size {
+                left_out[j] = left_out[j - 1] + 1;
+            }
+        }
+    }
+
+    SignatureCheck::Invalid {
+        suspects: Vec::new(),
+    }
+}
+
+impl AnnouncementGraph {
+    /// Checks an aggregated signature against every signature the block requires.
+    pub fn verify_signature(&self, signature: &Signature) -> SignatureCheck {
+        let requirements: Vec<&SignatureRequirement> =
+            self.signature_requirements().iter().collect();
+        verify_aggregated_signature(&requirements, signature)
+    }
+
+    /// Whether an aggregated signature combines exactly the signatures a spend
+    /// bundle requires.
+    pub fn bundle_signature_matches(
+        &self,
+        bundle: &SpendBundle<'_>,
+        signature: &Signature,
+    ) -> bool {
+        signature_matches(&self.signatures_required_by(bundle), signature)
+    }
+
+    /// The signatures required by the coins of a spend bundle, in the order
+    /// of its items.
+    pub fn signatures_required_by(&self, bundle: &SpendBundle<'_>) -> Vec<&SignatureRequirement> {
+        let coin_ids = bundle.coin_ids();
+        self.signature_requirements()
+            .iter()
+            .filter(|requirement| coin_ids.contains(&requirement.coin_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chia::bls::{sign, SecretKey};
+
+    use super::*;
+    use crate::{parse_items, GraphOptions};
+
+    fn signed_messages(json: &str, network: Network) -> Vec<String> {
+        let options = GraphOptions {
+            network,
+            ..Default::default()
+        };
+        let graph = AnnouncementGraph::with_options(parse_items(json).unwrap(), options);
+        assert_eq!(graph.signature_errors(), []);
+        graph
+            .signature_requirements()
+            .iter()
+            .map(|requirement| hex::encode(&requirement.signed_message))
+            .collect()
+    }
+
+    #[test]
+    fn agg_sig_me_in_block() {
+        let block = include_str!("../block.json");
+        let message = "d33449cda567e69a494b93c61aa3f4ca136ce5fce111355f8bc9cc557c2bd153";
+        let coin_id = "4054852d4e555ba974b32eba16c71da27a1fe7de8732abd76d36333153813e12";
+
+        assert_eq!(
+            signed_messages(block, Network::Mainnet)[0],
+            format!(
+                "{message}{coin_id}\
+                 ccd5bb71183532bff220ba46c268991a3ff07eb358e8255a65c30a2dce0e5fbb"
+            )
+        );
+        assert_eq!(
+            signed_messages(block, Network::Testnet11)[0],
+            format!(
+                "{message}{coin_id}\
+                 37a90eb5185a9c4439a91ddc98bbadce7b4feba060d50116a067de66bf236615"
+            )
+        );
+    }
+
+    #[test]
+    fn additional_data_hashes_opcode() {
+        let cases = [
+            (
+                Network::Mainnet,
+                AGG_SIG_PARENT,
+                "baf5d69c647c91966170302d18521b0a85663433d161e72c826ed08677b53a74",
+            ),
+            (
+                Network::Mainnet,
+                AGG_SIG_PUZZLE_AMOUNT,
+                "0f7d90dff0613e6901e24dae59f1e690f18b8f5fbdcf1bb192ac9deaf7de22ad",
+            ),
+            (
+                Network::Testnet11,
+                AGG_SIG_PARENT,
+                "c0754ae8602c47489b5394af8972c58238c4389d715f0585ca512d9428395e62",
+            ),
+            (
+                Network::Testnet11,
+                AGG_SIG_PUZZLE_AMOUNT,
+                "02c0ecb453e75bd77823dd0affd3f224d968012a8c6c6c423801cc30dd5eb347",
+            ),
+        ];
+        for (network, opcode, expected) in cases {
+            assert_eq!(hex::encode(network.additional_data(opcode)), expected);
+        }
+        for network in [Network::Mainnet, Network::Testnet11] {
+            assert_eq!(
+                network.additional_data(AGG_SIG_ME),
+                network.genesis_challenge()
+            );
+        }
+    }
+
+    #[test]
+    fn coin_fields_in_order() {
+        let public_key = hex::encode(SecretKey::from_seed(&[1; 32]).public_key().to_bytes());
+        let json = format!(
+            r#"[{{"Coin": "{parent}", "Coin_puzzle_hash": null, "Type": "standard",
+                 "Tags": null, "Spend": false, "Conditions": [], "Children": [
+                {{"Coin": "{coin}", "Coin_puzzle_hash": "{puzzle_hash}", "Coin_amount": 128,
+                  "Type": "standard", "Tags": null, "Spend": true, "Children": [],
+                  "Conditions": [
+                    {{"opcode": "AGG_SIG_PUZZLE_AMOUNT", "vars": ["{public_key}", "aa"]}},
+                    {{"opcode": "AGG_SIG_PARENT_AMOUNT", "vars": ["{public_key}", "aa"]}},
+                    {{"opcode": "AGG_SIG_PARENT_PUZZLE", "vars": ["{public_key}", "aa"]}},
+                    {{"opcode": "AGG_SIG_UNSAFE", "vars": ["{public_key}", "aa"]}}
+                  ]}}
+            ]}}]"#,
+            parent = "22".repeat(32),
+            coin = "33".repeat(32),
+            puzzle_hash = "11".repeat(32),
+        );
+        let (parent, puzzle_hash) = ("22".repeat(32), "11".repeat(32));
+
+        // An amount of 128 is encoded with a leading zero byte, so it isn't negative.
+        assert_eq!(
+            signed_messages(&json, Network::Mainnet),
+            [
+                format!(
+                    "aa{puzzle_hash}0080\
+                     0f7d90dff0613e6901e24dae59f1e690f18b8f5fbdcf1bb192ac9deaf7de22ad"
+                ),
+                format!(
+                    "aa{parent}0080\
+                     585796bd90bb553c0430b87027ffee08d88aba0162c6e1abbbcc6b583f2ae7f9"
+                ),
+                format!(
+                    "aa{parent}{puzzle_hash}\
+                     2ebfdae17b29d83bae476a25ea06f0c4bd57298faddbbc3ec5ad29b9b86ce5df"
+                ),
+                "aa".to_string(),
+            ]
+        );
+    }
+
+    /// A block of three spent coins, each with an `AGG_SIG_ME` by its own key.
+    fn signed_graph() -> (AnnouncementGraph, Vec<SecretKey>) {
+        let keys: Vec<SecretKey> = (1..=3).map(|i| SecretKey::from_seed(&[i; 32])).collect();
+        let items: Vec<String> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                format!(
+                    r#"{{"Coin": "{}", "Coin_puzzle_hash": null, "Type": "standard",
+                        "Tags": null, "Spend": true, "Children": [],
+                        "Conditions": [{{"opcode": "AGG_SIG_ME", "vars": ["{}", "0{i}"]}}]}}"#,
+                    hex::encode([i as u8 + 1; 32]),
+                    hex::encode(key.public_key().to_bytes())
+                )
+            })
+            .collect();
+        let items = parse_items(&format!("[{}]", items.join(","))).unwrap();
+        (AnnouncementGraph::new(items), keys)
+    }
+
+    fn aggregate(
+        graph: &AnnouncementGraph,
+        keys: &[SecretKey],
+        signed: impl Fn(usize) -> bool,
+    ) -> Signature {
+        let mut signature = Signature::default();
+        for (i, requirement) in graph.signature_requirements().iter().enumerate() {
+            if signed(i) {
+                signature += &sign(&keys[i], &requirement.signed_message);
+            }
+        }
+        signature
+    }
+
+    #[test]
+    fn verifies_aggregated_signature() {
+        let (graph, keys) = signed_graph();
+        let signature = aggregate(&graph, &keys, |_| true);
+        assert_eq!(graph.verify_signature(&signature), SignatureCheck::Valid);
+    }
+
+    #[test]
+    fn finds_coin_missing_from_signature() {
+        let (graph, keys) = signed_graph();
+        let signature = aggregate(&graph, &keys, |i| i != 1);
+        assert_eq!(
+            graph.verify_signature(&signature),
+            SignatureCheck::Invalid {
+                suspects: vec![Bytes32::new([2; 32])]
+            }
+        );
+    }
+
+    #[test]
+    fn finds_several_coins_missing_from_signature() {
+        let (graph, keys) = signed_graph();
+        let signature = aggregate(&graph, &keys, |i| i == 1);
+        assert_eq!(
+            graph.verify_signature(&signature),
+            SignatureCheck::Invalid {
+                suspects: vec![Bytes32::new([1; 32]), Bytes32::new([3; 32])]
+            }
+        );
+    }
+
+    #[test]
+    fn matches_signature_of_one_bundle() {
+        let (graph, keys) = signed_graph();
+        let signature = aggregate(&graph, &keys, |i| i == 1);
+        let matches: Vec<bool> = graph
+            .spend_bundles()
+            .iter()
+            .map(|bundle| graph.bundle_signature_matches(bundle, &signature))
+            .collect();
+        assert_eq!(matches, [false, true, false]);
+    }
+}
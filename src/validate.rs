@@ -0,0 +1,217 @@
+use std::fmt;
+
+use chia::protocol::{Bytes32, Coin};
+
+use crate::{AnnouncementKey, Announcements, Condition, Error, FlatItem};
+
+/// Checks that every `CREATE_COIN` names the child coin it actually creates,
+/// by recomputing the coin id from the parent, puzzle hash and amount.
+pub fn verify_child_coin_ids(items: &[FlatItem]) -> Vec<Error> {
+    let mut errors = Vec::new();
+
+    for FlatItem { item, path, .. } in items {
+        for (index, condition) in item.indexed_conditions() {
+            let Condition::CreateCoin {
+                puzzle_hash,
+                amount,
+                child_coin_id,
+                ..
+            } = condition
+            else {
+                continue;
+            };
+
+            let expected = Coin::new(item.coin_id, *puzzle_hash, *amount).coin_id();
+            if expected != *child_coin_id {
+                errors.push(Error::ChildCoinIdMismatch {
+                    path: format!("{path}.Conditions[{index}]"),
+                    coin_id: item.coin_id,
+                    index,
+                    expected,
+                    actual: *child_coin_id,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+/// Finds every announcement assertion that no announcement in the block
+/// satisfies. Under consensus, any one of these makes the spend bundle invalid.
+pub fn verify_assertions(items: &[FlatItem], announcements: &Announcements) -> Vec<Error> {
+    let mut errors = Vec::new();
+
+    for FlatItem { item, path, .. } in items {
+        for (index, condition) in item.indexed_conditions() {
+            let key = match condition {
+                Condition::AssertCoinAnnouncement { announcement_id } => {
+                    AnnouncementKey::coin(*announcement_id)
+                }
+                Condition::AssertPuzzleAnnouncement { announcement_id } => {
+                    AnnouncementKey::puzzle(*announcement_id)
+                }
+                _ => continue,
+            };
+
+            if announcements.creators(key).is_empty() {
+                errors.push(Error::UnsatisfiedAssertion {
+                    path: format!("{path}.Conditions[{index}]"),
+                    coin_id: item.coin_id,
+                    index,
+                    key,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+/// A condition asserting something about the coin itself, with the value
+/// asserted and the value the coin actually has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfAssertion {
+    CoinId { asserted: Bytes32, actual: Bytes32 },
+    ParentId { asserted: Bytes32, actual: Bytes32 },
+    PuzzleHash { asserted: Bytes32, actual: Bytes32 },
+    Amount { asserted: u64, actual: u64 },
+}
+
+impl SelfAssertion {
+    pub fn opcode(&self) -> &'static str {
+        match self {
+            Self::CoinId { .. } => "ASSERT_MY_COIN_ID",
+            Self::ParentId { .. } => "ASSERT_MY_PARENT_ID",
+            Self::PuzzleHash { .. } => "ASSERT_MY_PUZZLEHASH",
+            Self::Amount { .. } => "ASSERT_MY_AMOUNT",
+        }
+    }
+}
+
+impl fmt::Display for SelfAssertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CoinId { asserted, actual }
+            | Self::ParentId { asserted, actual }
+            | Self::PuzzleHash { asserted, actual } => {
+                write!(f, "asserts {asserted}, but the coin has {actual}")
+            }
+            Self::Amount { asserted, actual } => {
+                write!(f, "asserts {asserted}, but the coin has {actual}")
+            }
+        }
+    }
+}
+
+/// Checks the conditions that assert something about the spent coin itself
+/// against the item: `ASSERT_MY_COIN_ID` and `ASSERT_MY_PUZZLEHASH` against its
+/// own fields, and `ASSERT_MY_PARENT_ID` and `ASSERT_MY_AMOUNT` against the
+/// `CREATE_COIN` it came from. Under consensus, any failure makes the spend
+/// bundle invalid. Assertions about a parent or amount that isn't known are
+/// skipped.
+pub fn verify_self_assertions(items: &[FlatItem]) -> Vec<Error> {
+    let mut errors = Vec::new();
+
+    for FlatItem {
+        item,
+        path,
+        parent_coin_id,
+        origin,
+        ..
+    } in items
+    {
+        let parent_coin_id = origin
+            .map(|origin| origin.parent_coin_id)
+            .or(*parent_coin_id);
+
+        for (index, condition) in item.indexed_conditions() {
+            let mismatch = match *condition {
+                Condition::AssertMyCoinId { coin_id } => {
+                    (coin_id != item.coin_id).then_some(SelfAssertion::CoinId {
+                        asserted: coin_id,
+                        actual: item.coin_id,
+                    })
+                }
+                Condition::AssertMyParentId {
+                    parent_coin_id: asserted,
+                } => parent_coin_id
+                    .filter(|&actual| actual != asserted)
+                    .map(|actual| SelfAssertion::ParentId { asserted, actual }),
+                Condition::AssertMyPuzzlehash {
+                    puzzle_hash: asserted,
+                } => item
+                    .puzzle_hash
+                    .filter(|&actual| actual != asserted)
+                    .map(|actual| SelfAssertion::PuzzleHash { asserted, actual }),
+                Condition::AssertMyAmount { amount: asserted } => item
+                    .amount
+                    .filter(|&actual| actual != asserted)
+                    .map(|actual| SelfAssertion::Amount { asserted, actual }),
+                _ => continue,
+            };
+
+            if let Some(mismatch) = mismatch {
+                errors.push(Error::FailedSelfAssertion {
+                    path: format!("{path}.Conditions[{index}]"),
+                    coin_id: item.coin_id,
+                    index,
+                    mismatch,
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flatten_items, parse_items};
+
+    #[test]
+    fn accepts_child_coin_ids_in_block() {
+        let items = flatten_items(parse_items(include_str!("../block.json")).unwrap());
+        assert!(items.iter().any(|flat| flat
+            .item
+            .conditions
+            .iter()
+            .any(|condition| matches!(condition, Condition::CreateCoin { .. }))));
+        assert_eq!(verify_child_coin_ids(&items), []);
+    }
+
+    #[test]
+    fn finds_mismatched_child_coin_id() {
+        let parent = Bytes32::new([0x01; 32]);
+        let puzzle_hash = Bytes32::new([0x02; 32]);
+        let create_coin = |amount, child_coin_id: Bytes32| {
+            format!(
+                r#"{{"opcode": "CREATE_COIN", "send_puzzle": "{}", "amt": {amount},
+                     "child_coin_name": "{}", "send_address": ""}}"#,
+                hex::encode(puzzle_hash),
+                hex::encode(child_coin_id)
+            )
+        };
+        let child = Coin::new(parent, puzzle_hash, 128).coin_id();
+        let json = format!(
+            r#"[{{"Coin": "{}", "Coin_puzzle_hash": null, "Type": "standard", "Tags": null,
+                  "Spend": true, "Children": [], "Conditions": [{}, {}]}}]"#,
+            hex::encode(parent),
+            create_coin(128, child),
+            create_coin(129, child)
+        );
+        let items = flatten_items(parse_items(&json).unwrap());
+
+        assert_eq!(
+            verify_child_coin_ids(&items),
+            [Error::ChildCoinIdMismatch {
+                path: "$[0].Conditions[1]".to_string(),
+                coin_id: parent,
+                index: 1,
+                expected: Coin::new(parent, puzzle_hash, 129).coin_id(),
+                actual: child,
+            }]
+        );
+    }
+}